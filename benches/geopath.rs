@@ -0,0 +1,135 @@
+//! Geopath parsing, against the original split + itertools + Decimal implementation.
+//!
+//! Run with `--features f64-geopath` to compare the f64 parser as well.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use itertools::Itertools;
+use ptv::{Geopath, RoutesIdResponse, parse_path};
+use rust_decimal::Decimal;
+
+/// The Alamein line, as returned with include_geopath=true
+const GEOPATH: &str = include_str!("alamein_geopath.json");
+
+/// `helpers::deserialize_path` as it was before the hand-written parser
+fn legacy_parse_path(path: &str) -> Result<Vec<(Decimal, Decimal)>, String> {
+    path.split(' ')
+        .chunks(2)
+        .into_iter()
+        .map(|mut chunk| {
+            let lat = chunk
+                .next()
+                .and_then(|x| x.split(',').next())
+                .ok_or("missing latitude")?
+                .parse::<Decimal>()
+                .map_err(|e| format!("could not parse Decimal: {}", e))?;
+            let lon = chunk
+                .next()
+                .and_then(|x| x.split(',').next())
+                .ok_or("missing longitude")?
+                .parse::<Decimal>()
+                .map_err(|e| format!("could not parse Decimal: {}", e))?;
+            Ok((lat, lon))
+        })
+        .collect()
+}
+
+fn paths() -> Vec<String> {
+    let geopath: serde_json::Value = serde_json::from_str(GEOPATH).unwrap();
+    geopath["paths"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|path| path.as_str().unwrap().to_string())
+        .collect()
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let paths = paths();
+    let mut group = c.benchmark_group("parse_path");
+
+    group.bench_function("legacy", |b| {
+        b.iter(|| {
+            for path in &paths {
+                black_box(legacy_parse_path(black_box(path)).unwrap());
+            }
+        })
+    });
+
+    group.bench_function("current", |b| {
+        b.iter(|| {
+            for path in &paths {
+                black_box(parse_path(black_box(path)).unwrap());
+            }
+        })
+    });
+
+    #[cfg(feature = "f64-geopath")]
+    group.bench_function("f64", |b| {
+        b.iter(|| {
+            for path in &paths {
+                black_box(ptv::parse_path_f64(black_box(path)).unwrap());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+/// A routes/{route_id} response for the Alamein line with include_geopath=true
+fn route_response() -> String {
+    let geopath: serde_json::Value = serde_json::from_str(GEOPATH).unwrap();
+    serde_json::json!({
+        "route": {
+            "route_service_status": {
+                "description": "Good Service",
+                "timestamp": "2024-05-02T08:00:00+00:00"
+            },
+            "route_type": 0,
+            "route_id": 1,
+            "route_name": "Alamein",
+            "route_number": "",
+            "route_gtfs_id": "2-ALM",
+            "geopath": [geopath]
+        },
+        "status": { "version": "3.0", "health": 1 }
+    })
+    .to_string()
+}
+
+fn bench_deserialize(c: &mut Criterion) {
+    c.bench_function("deserialize_geopath", |b| {
+        b.iter(|| black_box(serde_json::from_str::<Geopath>(black_box(GEOPATH)).unwrap()))
+    });
+
+    #[cfg(feature = "f64-geopath")]
+    c.bench_function("deserialize_geopath_f64", |b| {
+        b.iter(|| black_box(serde_json::from_str::<ptv::GeopathF64>(black_box(GEOPATH)).unwrap()))
+    });
+
+    // Whole responses, as the Client methods deserialize them
+    let response = route_response();
+    let mut group = c.benchmark_group("deserialize_routes_id");
+
+    group.bench_function("decimal", |b| {
+        b.iter(|| {
+            black_box(serde_json::from_str::<RoutesIdResponse>(black_box(&response)).unwrap())
+        })
+    });
+
+    #[cfg(feature = "f64-geopath")]
+    group.bench_function("f64", |b| {
+        b.iter(|| {
+            black_box(
+                serde_json::from_str::<RoutesIdResponse<ptv::GeopathF64>>(black_box(&response))
+                    .unwrap(),
+            )
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_deserialize);
+criterion_main!(benches);
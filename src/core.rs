@@ -224,7 +224,7 @@ impl Client {
     /// View the stopping pattern for a specific tip / service run
     pub async fn patterns_run_route(
         &self,
-        run_ref: &str,
+        run_ref: &RunRef,
         route_type: RouteType,
         options: PatternsRunRouteOpts,
     ) -> Result<PatternResponse> {
@@ -257,7 +257,7 @@ impl Client {
     /* > Runs */
 
     /// View all trip/service runs for a specific run_ref
-    pub async fn runs_ref(&self, run_ref: &str, options: RunsRefOpts) -> Result<RunsResponse> {
+    pub async fn runs_ref(&self, run_ref: &RunRef, options: RunsRefOpts) -> Result<RunsResponse> {
         self.rq(format!("v3/runs/{}?{}", run_ref, to_query(options)))
             .await
     }
@@ -271,7 +271,7 @@ impl Client {
     /// View all trip/service runs for a specific run_ref and route type
     pub async fn runs_ref_type(
         &self,
-        run_ref: &str,
+        run_ref: &RunRef,
         route_type: RouteType,
         options: RunsRefOpts,
     ) -> Result<RunsResponse> {
@@ -324,3 +324,93 @@ impl Client {
         .await
     }
 }
+
+/// The requests that can include geopaths, with them parsed as [`GeopathF64`]
+#[cfg(feature = "f64-geopath")]
+impl Client {
+    /// [`Client::departures_stop`], with geopaths as [`GeopathF64`]
+    pub async fn departures_stop_f64(
+        &self,
+        route_type: RouteType,
+        stop_id: StopId,
+        options: DeparturesStopOpts,
+    ) -> Result<DeparturesResponse<GeopathF64>> {
+        self.rq(format!(
+            "v3/departures/route_type/{}/stop/{}?{}",
+            route_type,
+            stop_id,
+            to_query(options)
+        ))
+        .await
+    }
+
+    /// [`Client::departures_stop_route`], with geopaths as [`GeopathF64`]
+    pub async fn departures_stop_route_f64(
+        &self,
+        route_type: RouteType,
+        route_id: RouteId,
+        stop_id: StopId,
+        options: DeparturesStopRouteOpts,
+    ) -> Result<DeparturesResponse<GeopathF64>> {
+        self.rq(format!(
+            "v3/departures/route_type/{}/stop/{}/route/{}?{}",
+            route_type,
+            route_id,
+            stop_id,
+            to_query(options)
+        ))
+        .await
+    }
+
+    /// [`Client::patterns_run_route`], with geopaths as [`GeopathF64`]
+    pub async fn patterns_run_route_f64(
+        &self,
+        run_ref: &RunRef,
+        route_type: RouteType,
+        options: PatternsRunRouteOpts,
+    ) -> Result<PatternResponse<GeopathF64>> {
+        self.rq(format!(
+            "v3/pattern/run/{}/route_type/{}?{}",
+            run_ref,
+            route_type,
+            to_query(options)
+        ))
+        .await
+    }
+
+    /// [`Client::routes_id`], with geopaths as [`GeopathF64`]
+    pub async fn routes_id_f64(
+        &self,
+        route_id: RouteId,
+        options: RouteIdOpts,
+    ) -> Result<RoutesIdResponse<GeopathF64>> {
+        self.rq(format!("v3/routes/{}?{}", route_id, to_query(options)))
+            .await
+    }
+
+    /// [`Client::runs_ref`], with geopaths as [`GeopathF64`]
+    pub async fn runs_ref_f64(
+        &self,
+        run_ref: &RunRef,
+        options: RunsRefOpts,
+    ) -> Result<RunsResponse<GeopathF64>> {
+        self.rq(format!("v3/runs/{}?{}", run_ref, to_query(options)))
+            .await
+    }
+
+    /// [`Client::runs_ref_type`], with geopaths as [`GeopathF64`]
+    pub async fn runs_ref_type_f64(
+        &self,
+        run_ref: &RunRef,
+        route_type: RouteType,
+        options: RunsRefOpts,
+    ) -> Result<RunsResponse<GeopathF64>> {
+        self.rq(format!(
+            "v3/runs/{}/route_type/{}?{}",
+            run_ref,
+            route_type,
+            to_query(options)
+        ))
+        .await
+    }
+}
@@ -0,0 +1,134 @@
+//! Values derived from a departure's scheduled and estimated times,
+//! so you don't have to keep working them out yourself.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::ty::{Departure, DeparturesResponse, DirectionId};
+
+/// How a departure is running against its timetable
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Punctuality {
+    Early,
+    OnTime,
+    Late,
+    VeryLate,
+}
+
+/// Where the line is drawn between each [`Punctuality`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PunctualityThresholds {
+    /// Departures leaving at least this far ahead of schedule are early
+    pub early: Duration,
+    /// Departures leaving at least this far behind schedule are late
+    pub late: Duration,
+    /// Departures leaving at least this far behind schedule are very late
+    pub very_late: Duration,
+}
+
+impl Default for PunctualityThresholds {
+    /// Roughly the PTV definition of on time: no more than 59 seconds early or 4:59 late.
+    fn default() -> Self {
+        PunctualityThresholds {
+            early: Duration::minutes(1),
+            late: Duration::minutes(5),
+            very_late: Duration::minutes(15),
+        }
+    }
+}
+
+impl Departure {
+    /// The best known departure time, the real-time estimate if there is one, otherwise the timetabled time
+    pub fn effective_departure(&self) -> Option<DateTime<Utc>> {
+        self.estimated_departure.or(self.scheduled_departure)
+    }
+
+    /// How far behind schedule the departure is (negative if early).
+    /// None without a real-time estimate
+    pub fn delay(&self) -> Option<Duration> {
+        Some(self.estimated_departure? - self.scheduled_departure?)
+    }
+
+    /// Whether there's a real-time estimate for the departure
+    pub fn is_realtime(&self) -> bool {
+        self.estimated_departure.is_some()
+    }
+
+    /// Whole minutes from `now` until the departure (negative once it has left)
+    pub fn minutes_until(&self, now: DateTime<Utc>) -> Option<i64> {
+        self.effective_departure()
+            .map(|departure| (departure - now).num_minutes())
+    }
+
+    /// Whether the departure has left as of `now`
+    pub fn is_departed(&self, now: DateTime<Utc>) -> bool {
+        self.effective_departure()
+            .is_some_and(|departure| departure <= now)
+    }
+
+    /// Classify the departure's delay. None without a real-time estimate
+    pub fn punctuality(&self, thresholds: &PunctualityThresholds) -> Option<Punctuality> {
+        let delay = self.delay()?;
+        Some(if delay <= -thresholds.early {
+            Punctuality::Early
+        } else if delay >= thresholds.very_late {
+            Punctuality::VeryLate
+        } else if delay >= thresholds.late {
+            Punctuality::Late
+        } else {
+            Punctuality::OnTime
+        })
+    }
+}
+
+impl DeparturesResponse {
+    /// Departures yet to leave as of `now`, soonest first
+    pub fn upcoming(&self, now: DateTime<Utc>) -> Vec<&Departure> {
+        let mut departures = self
+            .departures
+            .iter()
+            .filter(|d| d.effective_departure().is_some() && !d.is_departed(now))
+            .collect::<Vec<_>>();
+        departures.sort_by_key(|d| d.effective_departure());
+        departures
+    }
+
+    /// The next `n` departures in each direction of travel
+    pub fn next_per_direction(
+        &self,
+        now: DateTime<Utc>,
+        n: usize,
+    ) -> BTreeMap<DirectionId, Vec<&Departure>> {
+        self.next_grouped(now, n, |d| d.direction_id)
+    }
+
+    /// The next `n` departures from each platform.
+    /// Departures without a platform number (non-train modes) are grouped under None
+    pub fn next_per_platform(
+        &self,
+        now: DateTime<Utc>,
+        n: usize,
+    ) -> BTreeMap<Option<String>, Vec<&Departure>> {
+        self.next_grouped(now, n, |d| d.platform_number.clone())
+    }
+
+    fn next_grouped<K: Ord>(
+        &self,
+        now: DateTime<Utc>,
+        n: usize,
+        key: impl Fn(&Departure) -> K,
+    ) -> BTreeMap<K, Vec<&Departure>> {
+        let mut groups = BTreeMap::<K, Vec<&Departure>>::new();
+        if n == 0 {
+            return groups;
+        }
+        for departure in self.upcoming(now) {
+            let group = groups.entry(key(departure)).or_default();
+            if group.len() < n {
+                group.push(departure);
+            }
+        }
+        groups
+    }
+}
@@ -0,0 +1,246 @@
+//! Schematic line diagrams of a stopping pattern, rendered as SVG.
+//!
+//! Stops are drawn evenly spaced in the order the run calls at them,
+//! with skipped (express) stops greyed out and vehicles placed between the stops they're travelling between.
+
+use std::fmt::Write;
+
+use html_escape::encode_safe;
+
+use crate::ty::{PatternResponse, Run, Stop, StoppingPatternsStop};
+
+/// Which way the line runs across the diagram
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Orientation {
+    /// First stop at the top, names to the right of the line
+    #[default]
+    Vertical,
+    /// First stop on the left, names above the line at an angle
+    Horizontal,
+}
+
+/// Colours, sizes and layout for [`PatternResponse::line_diagram`].
+/// Colours are anything SVG accepts, e.g. "#0072ce" or "white"
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagramStyle {
+    pub orientation: Orientation,
+    /// Space between stops
+    pub stop_spacing: f64,
+    /// Space around the diagram
+    pub margin: f64,
+    /// Room left for stop names, beside (vertical) or above (horizontal) the line
+    pub label_space: f64,
+    pub background: Option<String>,
+    pub line_colour: String,
+    pub line_width: f64,
+    pub stop_radius: f64,
+    pub stop_fill: String,
+    /// Colour of skipped stops, their names and the line through them
+    pub skipped_colour: String,
+    pub vehicle_colour: String,
+    pub vehicle_radius: f64,
+    pub font_family: String,
+    pub font_size: f64,
+    pub text_colour: String,
+}
+
+impl Default for DiagramStyle {
+    fn default() -> Self {
+        DiagramStyle {
+            orientation: Orientation::Vertical,
+            stop_spacing: 40.0,
+            margin: 20.0,
+            label_space: 200.0,
+            background: None,
+            line_colour: "#0072ce".into(),
+            line_width: 6.0,
+            stop_radius: 7.0,
+            stop_fill: "white".into(),
+            skipped_colour: "#b4b4b4".into(),
+            vehicle_colour: "#ffb81c".into(),
+            vehicle_radius: 9.0,
+            font_family: "sans-serif".into(),
+            font_size: 14.0,
+            text_colour: "#333333".into(),
+        }
+    }
+}
+
+/// A stop on the diagram
+struct DiagramStop<'a> {
+    stop: &'a Stop,
+    skipped: bool,
+}
+
+impl DiagramStyle {
+    /// Where the `i`th stop goes, fractional positions falling between stops
+    fn position(&self, i: f64) -> (f64, f64) {
+        let along = self.margin + self.stop_radius.max(self.vehicle_radius) + i * self.stop_spacing;
+        let across = self.margin + self.vehicle_radius.max(self.stop_radius);
+        match self.orientation {
+            Orientation::Vertical => (across, along),
+            Orientation::Horizontal => (along, across + self.label_space),
+        }
+    }
+
+    fn size(&self, stops: usize) -> (f64, f64) {
+        let (x, y) = self.position(stops.saturating_sub(1) as f64);
+        let edge = self.margin + self.stop_radius.max(self.vehicle_radius);
+        match self.orientation {
+            Orientation::Vertical => (x + edge + self.label_space, y + edge),
+            Orientation::Horizontal => (x + edge, y + edge),
+        }
+    }
+}
+
+impl PatternResponse {
+    /// Stops in the order the run calls at them, each after any skipped on the way to it.
+    /// Skipped stops are only known if the pattern was requested with `include_skipped_stops`
+    fn diagram_stops(&self) -> Vec<DiagramStop<'_>> {
+        let mut departures = self.departures.iter().collect::<Vec<_>>();
+        departures.sort_by_key(|d| d.departure_sequence);
+
+        let mut stops = Vec::new();
+        for departure in departures {
+            // A departure's skipped stops are those passed through after the previous stop
+            for skipped in departure.skipped_stops.iter().flatten() {
+                stops.push(DiagramStop {
+                    stop: skipped,
+                    skipped: true,
+                });
+            }
+            if let Some(stop) = self.stops.get(&departure.stop_id) {
+                stops.push(DiagramStop {
+                    stop: &stop.stop,
+                    skipped: false,
+                });
+            }
+        }
+
+        // The first stop has nothing to have skipped before it
+        while stops.first().is_some_and(|s| s.skipped) {
+            stops.remove(0);
+        }
+        stops
+    }
+
+    /// Render the pattern as an SVG line diagram, with the vehicles of `runs` drawn where they are.
+    ///
+    /// Vehicles are placed using [`Run::snap_to_pattern`], so are left out unless the run has a
+    /// vehicle position and the pattern was requested with `include_geopath`.
+    pub fn line_diagram<'a>(
+        &self,
+        runs: impl IntoIterator<Item = &'a Run>,
+        style: &DiagramStyle,
+    ) -> String {
+        let stops = self.diagram_stops();
+        let (width, height) = style.size(stops.len());
+
+        let mut svg = String::new();
+        let _ = write!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.1}" height="{height:.1}" viewBox="0 0 {width:.1} {height:.1}" font-family="{}" font-size="{:.1}">"#,
+            encode_safe(&style.font_family),
+            style.font_size,
+        );
+
+        if let Some(background) = &style.background {
+            let _ = write!(
+                svg,
+                r#"<rect width="100%" height="100%" fill="{}"/>"#,
+                encode_safe(background)
+            );
+        }
+
+        // The line between each pair of stops, greyed out into and out of skipped ones
+        for (i, pair) in stops.windows(2).enumerate() {
+            let ((x1, y1), (x2, y2)) = (style.position(i as f64), style.position(i as f64 + 1.0));
+            let colour = match pair[0].skipped || pair[1].skipped {
+                true => &style.skipped_colour,
+                false => &style.line_colour,
+            };
+            let _ = write!(
+                svg,
+                r#"<line x1="{x1:.1}" y1="{y1:.1}" x2="{x2:.1}" y2="{y2:.1}" stroke="{}" stroke-width="{:.1}" stroke-linecap="round"/>"#,
+                encode_safe(colour),
+                style.line_width,
+            );
+        }
+
+        for (i, stop) in stops.iter().enumerate() {
+            let (x, y) = style.position(i as f64);
+            let (stroke, text) = match stop.skipped {
+                true => (&style.skipped_colour, &style.skipped_colour),
+                false => (&style.line_colour, &style.text_colour),
+            };
+            let _ = write!(
+                svg,
+                r#"<circle cx="{x:.1}" cy="{y:.1}" r="{:.1}" fill="{}" stroke="{}" stroke-width="{:.1}"/>"#,
+                style.stop_radius,
+                encode_safe(&style.stop_fill),
+                encode_safe(stroke),
+                style.line_width / 2.0,
+            );
+
+            let offset = style.stop_radius.max(style.vehicle_radius) + style.font_size / 2.0;
+            let (label, transform) = match style.orientation {
+                Orientation::Vertical => (
+                    format!(
+                        r#"x="{:.1}" y="{:.1}" dominant-baseline="middle""#,
+                        x + offset,
+                        y
+                    ),
+                    String::new(),
+                ),
+                Orientation::Horizontal => (
+                    format!(r#"x="{:.1}" y="{:.1}""#, x, y - offset),
+                    format!(r#" transform="rotate(-45 {:.1} {:.1})""#, x, y - offset),
+                ),
+            };
+            let _ = write!(
+                svg,
+                r#"<text {label}{transform} fill="{}">{}</text>"#,
+                encode_safe(text),
+                encode_safe(&stop.stop.name),
+            );
+        }
+
+        for run in runs {
+            let Some(position) = run.snap_to_pattern(self) else {
+                continue;
+            };
+            // Patterns can call at a stop more than once (loops), so look for the next stop after the previous one
+            let index = |stop: Option<&StoppingPatternsStop>, after: usize| {
+                let stop = stop?;
+                stops[after..]
+                    .iter()
+                    .position(|s| !s.skipped && s.stop.id == stop.stop.id)
+                    .map(|i| after + i)
+            };
+            let previous = index(position.previous_stop, 0);
+            let next = index(position.next_stop, previous.map_or(0, |i| i + 1));
+
+            let along = match (previous, next, position.progress) {
+                (Some(previous), Some(next), Some(progress)) => {
+                    previous as f64 + (next as f64 - previous as f64) * progress
+                }
+                (Some(previous), None, _) => previous as f64,
+                (None, Some(next), _) => next as f64,
+                _ => continue,
+            };
+
+            let (x, y) = style.position(along);
+            let _ = write!(
+                svg,
+                r#"<circle cx="{x:.1}" cy="{y:.1}" r="{:.1}" fill="{}" stroke="{}" stroke-width="2"><title>{}</title></circle>"#,
+                style.vehicle_radius,
+                encode_safe(&style.vehicle_colour),
+                encode_safe(&style.line_colour),
+                encode_safe(&format!("{} to {}", run.run_ref, run.destination_name)),
+            );
+        }
+
+        svg.push_str("</svg>");
+        svg
+    }
+}
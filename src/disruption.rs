@@ -0,0 +1,364 @@
+//! Helpers for working with disruptions.
+
+use std::{borrow::Cow, collections::BTreeSet, slice};
+
+use chrono::{DateTime, Timelike, Utc};
+use itertools::Itertools;
+
+use crate::{
+    time::MelbourneTime,
+    ty::{
+        Departure, DeparturesResponse, Disruption, DisruptionModes, DisruptionRoute, Disruptions,
+        DisruptionsResponse,
+    },
+};
+
+impl Disruption {
+    /// The description as plain text, for places that can't render HTML (LED signs, SMS etc.)
+    ///
+    /// Tags are stripped (keeping link text), entities are decoded and
+    /// line breaks, paragraphs and list items become newlines.
+    pub fn plain_text(&self) -> String {
+        html_to_text(&self.description)
+    }
+
+    /// The description with anything unsafe removed, fit for embedding in a web page
+    pub fn sanitized_html(&self) -> String {
+        ammonia::clean(&self.description)
+    }
+
+    /// A single line summary of at most `max_chars` characters, cut at a word boundary.
+    /// Uses the title, or the description if there isn't one
+    pub fn summary(&self, max_chars: usize) -> String {
+        let text = match html_to_text(&self.title) {
+            title if title.is_empty() => self.plain_text(),
+            title => title,
+        };
+
+        truncate_words(&text.split_whitespace().join(" "), max_chars)
+    }
+}
+
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            // Not actually a tag
+            text.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+
+        let tag = rest[start + 1..start + end].trim().to_lowercase();
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        if matches!(name, "br" | "p" | "div" | "li" | "tr")
+            && (name == "br" || tag.starts_with('/'))
+        {
+            text.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    html_escape::decode_html_entities(&text)
+        .replace('\u{a0}', " ")
+        .lines()
+        .map(|line| line.split_whitespace().join(" "))
+        .filter(|line| !line.is_empty())
+        .join("\n")
+}
+
+fn truncate_words(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+
+    // Leave room for the ellipsis, and don't cut a word in half unless it's the only one
+    let cut = text
+        .char_indices()
+        .nth(max_chars - 1)
+        .map_or(text.len(), |(i, _)| i);
+    let mut head = &text[..cut];
+    if !text[cut..].starts_with(char::is_whitespace) {
+        head = head
+            .rsplit_once(char::is_whitespace)
+            .map_or(head, |(head, _)| head);
+    }
+
+    let head = head.trim_end_matches(|c: char| c.is_whitespace() || c.is_ascii_punctuation());
+    format!("{head}\u{2026}")
+}
+
+/// The mode a disruption is listed under in [`Disruptions`]:
+/// one we know about, or the name of a mode in [`Disruptions::other`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DisruptionModeKey<'a> {
+    Known(DisruptionModes),
+    Other(Cow<'a, str>),
+}
+
+impl From<DisruptionModes> for DisruptionModeKey<'_> {
+    fn from(mode: DisruptionModes) -> Self {
+        DisruptionModeKey::Known(mode)
+    }
+}
+
+impl<'a> From<&'a str> for DisruptionModeKey<'a> {
+    fn from(name: &'a str) -> Self {
+        DisruptionModeKey::Other(Cow::Borrowed(name))
+    }
+}
+
+impl Disruptions {
+    fn known(&self, mode: DisruptionModes) -> &[Disruption] {
+        match mode {
+            DisruptionModes::General => &self.general,
+            DisruptionModes::MetroTrain => &self.metro_train,
+            DisruptionModes::MetroTram => &self.metro_tram,
+            DisruptionModes::MetroBus => &self.metro_bus,
+            DisruptionModes::RegionalTrain => &self.regional_train,
+            DisruptionModes::RegionalCoach => &self.regional_coach,
+            DisruptionModes::RegionalBus => &self.regional_bus,
+            DisruptionModes::SchoolBus => &self.school_bus,
+            DisruptionModes::Telebus => &self.telebus,
+            DisruptionModes::NightBus => &self.night_bus,
+            DisruptionModes::Ferry => &self.ferry,
+            DisruptionModes::InterstateTrain => &self.interstate_train,
+            DisruptionModes::Skybus => &self.skybus,
+            DisruptionModes::Taxi => &self.taxi,
+        }
+    }
+
+    /// Disruptions for a single mode, either a [`DisruptionModes`] or the name of one in [`Disruptions::other`]
+    pub fn by_mode<'k>(
+        &self,
+        mode: impl Into<DisruptionModeKey<'k>>,
+    ) -> slice::Iter<'_, Disruption> {
+        match mode.into() {
+            DisruptionModeKey::Known(mode) => self.known(mode).iter(),
+            DisruptionModeKey::Other(name) => self
+                .other
+                .get(name.as_ref())
+                .map_or([].iter(), |disruptions| disruptions.iter()),
+        }
+    }
+
+    /// Every disruption alongside the mode it was listed under.
+    /// Disruptions affecting several modes will appear once for each
+    pub fn iter(&self) -> impl Iterator<Item = (DisruptionModeKey<'_>, &Disruption)> {
+        let known = DisruptionModes::ALL.into_iter().flat_map(move |mode| {
+            self.known(mode)
+                .iter()
+                .map(move |d| (DisruptionModeKey::Known(mode), d))
+        });
+        let other = self.other.iter().flat_map(|(name, disruptions)| {
+            disruptions
+                .iter()
+                .map(move |d| (DisruptionModeKey::from(name.as_str()), d))
+        });
+
+        known.chain(other)
+    }
+
+    /// Every disruption, once each (by disruption_id)
+    pub fn unique(&self) -> impl Iterator<Item = &Disruption> {
+        let mut seen = BTreeSet::new();
+        self.iter()
+            .filter_map(move |(_, d)| seen.insert(d.disruption_id).then_some(d))
+    }
+
+    /// Number of disruptions across all modes, counting duplicates
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+impl<'a> IntoIterator for &'a Disruptions {
+    type Item = (DisruptionModeKey<'a>, &'a Disruption);
+    type IntoIter = Box<dyn Iterator<Item = Self::Item> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+impl IntoIterator for Disruptions {
+    type Item = (DisruptionModeKey<'static>, Disruption);
+    type IntoIter = Box<dyn Iterator<Item = Self::Item>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let Disruptions {
+            general,
+            metro_train,
+            metro_tram,
+            metro_bus,
+            regional_train,
+            regional_coach,
+            regional_bus,
+            school_bus,
+            telebus,
+            night_bus,
+            ferry,
+            interstate_train,
+            skybus,
+            taxi,
+            other,
+        } = self;
+
+        let known = [
+            general,
+            metro_train,
+            metro_tram,
+            metro_bus,
+            regional_train,
+            regional_coach,
+            regional_bus,
+            school_bus,
+            telebus,
+            night_bus,
+            ferry,
+            interstate_train,
+            skybus,
+            taxi,
+        ]
+        .into_iter()
+        .zip(DisruptionModes::ALL)
+        .flat_map(|(disruptions, mode)| {
+            disruptions
+                .into_iter()
+                .map(move |d| (DisruptionModeKey::Known(mode), d))
+        });
+        let other = other.into_iter().flat_map(|(name, disruptions)| {
+            disruptions
+                .into_iter()
+                .map(move |d| (DisruptionModeKey::Other(Cow::Owned(name.clone())), d))
+        });
+
+        Box::new(known.chain(other))
+    }
+}
+
+/// A departure, with the disruptions that apply to it
+#[derive(Debug)]
+pub struct DisruptedDeparture<'a> {
+    pub departure: &'a Departure,
+    pub disruptions: Vec<AppliedDisruption<'a>>,
+}
+
+/// A disruption that applies to a departure
+#[derive(Debug)]
+pub struct AppliedDisruption<'a> {
+    pub disruption: &'a Disruption,
+    /// The departure lists the disruption in its disruption_ids
+    pub referenced: bool,
+    /// The departure falls between the disruption's from_date and to_date
+    pub in_effect: bool,
+}
+
+impl DisruptedDeparture<'_> {
+    /// Whether any of the disruptions are in effect at the time of the departure
+    pub fn is_disrupted(&self) -> bool {
+        self.disruptions.iter().any(|d| d.in_effect)
+    }
+}
+
+impl Disruption {
+    /// Whether `time` falls between the disruption's from_date and to_date (open ended if there's no to_date)
+    pub fn is_in_effect(&self, time: DateTime<Utc>) -> bool {
+        self.from_date <= time && self.to_date.is_none_or(|to| time <= to)
+    }
+
+    /// Whether the disruption applies to a departure, going by its routes, directions, service times and stops.
+    ///
+    /// A disruption's service time is matched against the departure's scheduled time at its stop,
+    /// so services singled out by their departure time from elsewhere on the line won't match.
+    /// Disruptions with neither routes nor stops (e.g. network wide notices) never match.
+    pub fn applies_to(&self, departure: &Departure) -> bool {
+        if self.routes.is_empty() && self.stops.is_empty() {
+            return false;
+        }
+
+        let route = self.routes.is_empty()
+            || self
+                .routes
+                .iter()
+                .any(|route| route_applies_to(route, departure));
+        let stop = self.stops.is_empty() || self.stops.iter().any(|s| s.id == departure.stop_id);
+
+        route && stop
+    }
+}
+
+fn route_applies_to(route: &DisruptionRoute, departure: &Departure) -> bool {
+    if route.route.id != departure.route_id {
+        return false;
+    }
+
+    let Some(direction) = &route.direction else {
+        return true;
+    };
+    if direction.id != departure.direction_id {
+        return false;
+    }
+
+    match (direction.service_time, departure.scheduled_departure) {
+        (Some(service_time), Some(scheduled)) => {
+            let scheduled = scheduled.to_melbourne();
+            (scheduled.hour(), scheduled.minute()) == (service_time.hour(), service_time.minute())
+        }
+        _ => true,
+    }
+}
+
+impl DeparturesResponse {
+    /// Link each departure to the disruptions that apply to it,
+    /// from both `disruptions` and any expanded in this response.
+    pub fn link_disruptions<'a>(
+        &'a self,
+        disruptions: &'a DisruptionsResponse,
+    ) -> Vec<DisruptedDeparture<'a>> {
+        let mut seen = BTreeSet::new();
+        let candidates = disruptions
+            .disruptions
+            .unique()
+            .chain(self.disruptions.values())
+            .filter(|d| seen.insert(d.disruption_id))
+            .collect::<Vec<_>>();
+
+        self.departures
+            .iter()
+            .map(|departure| DisruptedDeparture {
+                departure,
+                disruptions: candidates
+                    .iter()
+                    .filter_map(|disruption| {
+                        let referenced =
+                            departure.disruption_ids.contains(&disruption.disruption_id);
+                        (referenced || disruption.applies_to(departure)).then(|| {
+                            AppliedDisruption {
+                                disruption,
+                                referenced,
+                                in_effect: departure
+                                    .effective_departure()
+                                    .is_some_and(|time| disruption.is_in_effect(time)),
+                            }
+                        })
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
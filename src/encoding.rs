@@ -0,0 +1,256 @@
+//! Compact encodings of route geopaths, for shipping them to clients
+//! without the multi-kilobyte coordinate strings the API returns.
+//!
+//! - [Encoded polylines](https://developers.google.com/maps/documentation/utilities/polylinealgorithm),
+//!   one string per path, understood by most mapping libraries.
+//! - A delta-encoded binary form (see [`Geopath::to_bytes`]), carrying the whole geopath.
+//!
+//! Both store coordinates to a fixed number of decimal places (`precision`), so
+//! round-tripping gives back the original coordinates rounded to that precision.
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{Datelike, NaiveDate};
+
+use crate::{
+    helpers::{coordinate_from_scaled, coordinate_to_scaled},
+    ty::{Coordinate, DirectionId, Geopath},
+};
+
+/// Decimal places kept by the Google polyline algorithm, ~1m
+pub const POLYLINE_PRECISION: u32 = 5;
+
+/// Highest precision supported, beyond this the scaled coordinates can overflow
+pub const MAX_PRECISION: u32 = 15;
+
+/// Version byte at the start of the binary form
+const BINARY_VERSION: u8 = 1;
+
+fn check_precision(precision: u32) -> Result<()> {
+    if precision > MAX_PRECISION {
+        bail!("Precision {precision} is above the maximum of {MAX_PRECISION}");
+    }
+    Ok(())
+}
+
+fn out_of_range(value: Coordinate) -> anyhow::Error {
+    anyhow!("Coordinate {value} is out of range")
+}
+
+fn scale(value: Coordinate, precision: u32) -> Result<i64> {
+    coordinate_to_scaled(value, precision).ok_or_else(|| out_of_range(value))
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+/// Coordinates as deltas from the previous point, the first from (0, 0)
+fn deltas(path: &[(Coordinate, Coordinate)], precision: u32) -> Result<Vec<i64>> {
+    let mut previous = (0, 0);
+    let mut deltas = Vec::with_capacity(path.len() * 2);
+    for (latitude, longitude) in path {
+        let point = (scale(*latitude, precision)?, scale(*longitude, precision)?);
+        for (value, scaled, previous) in [
+            (latitude, point.0, previous.0),
+            (longitude, point.1, previous.1),
+        ] {
+            deltas.push(
+                scaled
+                    .checked_sub(previous)
+                    .ok_or_else(|| out_of_range(*value))?,
+            );
+        }
+        previous = point;
+    }
+    Ok(deltas)
+}
+
+/// Coordinates back from [`deltas`]
+fn undeltas(deltas: &[i64], precision: u32) -> Vec<(Coordinate, Coordinate)> {
+    let mut point = (0i64, 0i64);
+    deltas
+        .chunks_exact(2)
+        .map(|delta| {
+            point = (
+                point.0.wrapping_add(delta[0]),
+                point.1.wrapping_add(delta[1]),
+            );
+            (
+                coordinate_from_scaled(point.0, precision),
+                coordinate_from_scaled(point.1, precision),
+            )
+        })
+        .collect()
+}
+
+/// Encode (latitude, longitude) pairs as a polyline
+pub fn encode_polyline(path: &[(Coordinate, Coordinate)], precision: u32) -> Result<String> {
+    check_precision(precision)?;
+
+    let mut encoded = String::new();
+    for delta in deltas(path, precision)? {
+        let mut value = zigzag(delta);
+        while value >= 0x20 {
+            encoded.push(char::from((0x20 | (value & 0x1f)) as u8 + 63));
+            value >>= 5;
+        }
+        encoded.push(char::from(value as u8 + 63));
+    }
+    Ok(encoded)
+}
+
+/// Decode a polyline into (latitude, longitude) pairs
+pub fn decode_polyline(encoded: &str, precision: u32) -> Result<Vec<(Coordinate, Coordinate)>> {
+    check_precision(precision)?;
+
+    let mut deltas = Vec::new();
+    let (mut value, mut shift) = (0u64, 0);
+    for byte in encoded.bytes() {
+        let chunk = byte
+            .checked_sub(63)
+            .filter(|chunk| *chunk < 0x40)
+            .ok_or_else(|| anyhow!("Invalid polyline character '{}'", char::from(byte)))?;
+        if shift > 60 {
+            bail!("Polyline value is too long");
+        }
+
+        value |= u64::from(chunk & 0x1f) << shift;
+        shift += 5;
+        if chunk < 0x20 {
+            deltas.push(unzigzag(value));
+            (value, shift) = (0, 0);
+        }
+    }
+
+    if shift != 0 || deltas.len() % 2 != 0 {
+        bail!("Polyline ends part way through a coordinate");
+    }
+    Ok(undeltas(&deltas, precision))
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = bytes
+            .split_first()
+            .ok_or_else(|| anyhow!("Unexpected end of geopath bytes"))?;
+        *bytes = rest;
+
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("Varint is too long")
+}
+
+fn read_date(bytes: &mut &[u8]) -> Result<NaiveDate> {
+    let days = i32::try_from(unzigzag(read_varint(bytes)?))?;
+    NaiveDate::from_num_days_from_ce_opt(days).ok_or_else(|| anyhow!("Invalid date {days}"))
+}
+
+impl Geopath {
+    /// Each path as an encoded polyline, see [`encode_polyline`]
+    pub fn to_polyline(&self, precision: u32) -> Result<Vec<String>> {
+        self.paths
+            .iter()
+            .map(|path| encode_polyline(path, precision))
+            .collect()
+    }
+
+    /// Build a geopath from encoded polylines, one per path
+    pub fn from_polyline(
+        direction_id: DirectionId,
+        valid_from: NaiveDate,
+        valid_to: NaiveDate,
+        polylines: impl IntoIterator<Item = impl AsRef<str>>,
+        precision: u32,
+    ) -> Result<Geopath> {
+        Ok(Geopath {
+            direction_id,
+            valid_from,
+            valid_to,
+            paths: polylines
+                .into_iter()
+                .map(|polyline| decode_polyline(polyline.as_ref(), precision))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// The whole geopath in a compact binary form.
+    ///
+    /// Integers are LEB128 varints, signed ones zigzag encoded:
+    /// version, precision, direction id, valid from and to (days from the common era), number of paths,
+    /// then per path the number of points and each point's (latitude, longitude) delta from the previous one.
+    pub fn to_bytes(&self, precision: u32) -> Result<Vec<u8>> {
+        check_precision(precision)?;
+
+        let mut bytes = vec![BINARY_VERSION, precision as u8];
+        write_varint(&mut bytes, zigzag(self.direction_id.0.into()));
+        write_varint(
+            &mut bytes,
+            zigzag(self.valid_from.num_days_from_ce().into()),
+        );
+        write_varint(&mut bytes, zigzag(self.valid_to.num_days_from_ce().into()));
+
+        write_varint(&mut bytes, self.paths.len() as u64);
+        for path in &self.paths {
+            write_varint(&mut bytes, path.len() as u64);
+            for delta in deltas(path, precision)? {
+                write_varint(&mut bytes, zigzag(delta));
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Read a geopath back from [`Geopath::to_bytes`]
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Geopath> {
+        let [version, precision, rest @ ..] = bytes else {
+            bail!("Unexpected end of geopath bytes");
+        };
+        if *version != BINARY_VERSION {
+            bail!("Unsupported geopath version {version}");
+        }
+        let precision = u32::from(*precision);
+        check_precision(precision)?;
+        bytes = rest;
+
+        let direction_id = DirectionId(i32::try_from(unzigzag(read_varint(&mut bytes)?))?);
+        let valid_from = read_date(&mut bytes)?;
+        let valid_to = read_date(&mut bytes)?;
+
+        // Counts aren't trusted for preallocating, a corrupt count just runs out of bytes
+        let mut paths = Vec::new();
+        for _ in 0..read_varint(&mut bytes)? {
+            let mut deltas = Vec::new();
+            for _ in 0..read_varint(&mut bytes)? {
+                deltas.push(unzigzag(read_varint(&mut bytes)?));
+                deltas.push(unzigzag(read_varint(&mut bytes)?));
+            }
+            paths.push(undeltas(&deltas, precision));
+        }
+
+        if !bytes.is_empty() {
+            bail!("{} trailing bytes after geopath", bytes.len());
+        }
+
+        Ok(Geopath {
+            direction_id,
+            valid_from,
+            valid_to,
+            paths,
+        })
+    }
+}
@@ -0,0 +1,255 @@
+//! KML (Google Earth) and GPX (GPS units) export of route shapes, stops and vehicle traces.
+//!
+//! Build up a document with [`Kml`] or [`Gpx`], then write it out with `to_string()`.
+
+use std::fmt::{self, Write};
+
+use geo_types::Point;
+use html_escape::{encode_double_quoted_attribute, encode_text};
+
+use crate::{
+    helpers::coordinate_to_f64,
+    spatial::IndexedStop,
+    ty::{Geopath, RouteWithGeoPath, Stop, VehiclePosition},
+};
+
+/// Samples with a location, in time order
+fn track_points<'a>(
+    positions: impl IntoIterator<Item = &'a VehiclePosition>,
+) -> Vec<(&'a VehiclePosition, Point<f64>)> {
+    let mut points = positions
+        .into_iter()
+        .filter_map(|position| Some((position, position.coordinates()?)))
+        .collect::<Vec<_>>();
+    points.sort_by_key(|(position, _)| position.datetime);
+    points
+}
+
+fn timestamp(position: &VehiclePosition) -> String {
+    position
+        .datetime
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+/// "number name", or just the name for routes without a number
+fn route_name(route: &RouteWithGeoPath) -> String {
+    match route.route.number.as_str() {
+        "" => route.route.name.clone(),
+        number => format!("{number} {}", route.route.name),
+    }
+}
+
+/// A KML document
+#[derive(Debug, Clone, Default)]
+pub struct Kml {
+    name: String,
+    placemarks: Vec<String>,
+}
+
+impl Kml {
+    pub fn new(name: impl Into<String>) -> Self {
+        Kml {
+            name: name.into(),
+            placemarks: Vec::new(),
+        }
+    }
+
+    fn line_strings(geopath: &Geopath) -> String {
+        let mut kml = String::new();
+        for path in &geopath.paths {
+            kml.push_str("<LineString><tessellate>1</tessellate><coordinates>");
+            for (latitude, longitude) in path {
+                let _ = write!(
+                    kml,
+                    "{},{} ",
+                    coordinate_to_f64(*longitude),
+                    coordinate_to_f64(*latitude)
+                );
+            }
+            kml.push_str("</coordinates></LineString>");
+        }
+        kml
+    }
+
+    /// A placemark for the geopath, named `name`
+    pub fn add_geopath(&mut self, name: &str, geopath: &Geopath) -> &mut Self {
+        self.placemarks.push(format!(
+            "<Placemark><name>{}</name><description>Direction {}, valid {} to {}</description><MultiGeometry>{}</MultiGeometry></Placemark>",
+            encode_text(name),
+            geopath.direction_id,
+            geopath.valid_from,
+            geopath.valid_to,
+            Kml::line_strings(geopath),
+        ));
+        self
+    }
+
+    /// A placemark for each of the route's geopaths, named after the route
+    pub fn add_route(&mut self, route: &RouteWithGeoPath) -> &mut Self {
+        let name = route_name(route);
+        for geopath in route.geopath.iter().flatten() {
+            self.add_geopath(&name, geopath);
+        }
+        self
+    }
+
+    /// A point placemark for each stop, with its landmark as the description.
+    /// Stops without a location are skipped
+    pub fn add_stops<'a>(&mut self, stops: impl IntoIterator<Item = &'a Stop>) -> &mut Self {
+        for stop in stops {
+            let Some(point) = stop.location() else {
+                continue;
+            };
+            self.placemarks.push(format!(
+                "<Placemark><name>{}</name><description>{}</description><Point><coordinates>{},{}</coordinates></Point></Placemark>",
+                encode_text(&stop.name),
+                encode_text(&stop.landmark),
+                point.x(),
+                point.y(),
+            ));
+        }
+        self
+    }
+
+    /// A time-stamped track through the vehicle's positions, in time order.
+    /// Positions without a location are skipped
+    pub fn add_track<'a>(
+        &mut self,
+        name: &str,
+        positions: impl IntoIterator<Item = &'a VehiclePosition>,
+    ) -> &mut Self {
+        let points = track_points(positions);
+        let mut track = String::new();
+        for (position, _) in &points {
+            let _ = write!(track, "<when>{}</when>", timestamp(position));
+        }
+        for (_, point) in &points {
+            let _ = write!(track, "<gx:coord>{} {} 0</gx:coord>", point.x(), point.y());
+        }
+
+        self.placemarks.push(format!(
+            "<Placemark><name>{}</name><gx:Track>{track}</gx:Track></Placemark>",
+            encode_text(name),
+        ));
+        self
+    }
+}
+
+impl fmt::Display for Kml {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"<?xml version="1.0" encoding="UTF-8"?><kml xmlns="http://www.opengis.net/kml/2.2" xmlns:gx="http://www.google.com/kml/ext/2.2"><Document><name>{}</name>"#,
+            encode_text(&self.name)
+        )?;
+        for placemark in &self.placemarks {
+            f.write_str(placemark)?;
+        }
+        f.write_str("</Document></kml>")
+    }
+}
+
+/// A GPX 1.1 document
+#[derive(Debug, Clone, Default)]
+pub struct Gpx {
+    name: String,
+    // GPX wants waypoints before tracks, so they're kept apart
+    waypoints: Vec<String>,
+    tracks: Vec<String>,
+}
+
+impl Gpx {
+    pub fn new(name: impl Into<String>) -> Self {
+        Gpx {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// A track for the geopath, with a segment per path
+    pub fn add_geopath(&mut self, name: &str, geopath: &Geopath) -> &mut Self {
+        let mut track = format!("<trk><name>{}</name>", encode_text(name));
+        for path in &geopath.paths {
+            track.push_str("<trkseg>");
+            for (latitude, longitude) in path {
+                let _ = write!(
+                    track,
+                    r#"<trkpt lat="{}" lon="{}"/>"#,
+                    coordinate_to_f64(*latitude),
+                    coordinate_to_f64(*longitude)
+                );
+            }
+            track.push_str("</trkseg>");
+        }
+        track.push_str("</trk>");
+        self.tracks.push(track);
+        self
+    }
+
+    /// A track for each of the route's geopaths, named after the route
+    pub fn add_route(&mut self, route: &RouteWithGeoPath) -> &mut Self {
+        let name = route_name(route);
+        for geopath in route.geopath.iter().flatten() {
+            self.add_geopath(&name, geopath);
+        }
+        self
+    }
+
+    /// A waypoint for each stop, with its landmark as the description.
+    /// Stops without a location are skipped
+    pub fn add_stops<'a>(&mut self, stops: impl IntoIterator<Item = &'a Stop>) -> &mut Self {
+        for stop in stops {
+            let Some(point) = stop.location() else {
+                continue;
+            };
+            self.waypoints.push(format!(
+                r#"<wpt lat="{}" lon="{}"><name>{}</name><desc>{}</desc></wpt>"#,
+                point.y(),
+                point.x(),
+                encode_text(&stop.name),
+                encode_text(&stop.landmark),
+            ));
+        }
+        self
+    }
+
+    /// A time-stamped track through the vehicle's positions, in time order.
+    /// Positions without a location are skipped
+    pub fn add_track<'a>(
+        &mut self,
+        name: &str,
+        positions: impl IntoIterator<Item = &'a VehiclePosition>,
+    ) -> &mut Self {
+        let mut track = format!("<trk><name>{}</name><trkseg>", encode_text(name));
+        for (position, point) in track_points(positions) {
+            let _ = write!(
+                track,
+                r#"<trkpt lat="{}" lon="{}"><time>{}</time></trkpt>"#,
+                point.y(),
+                point.x(),
+                timestamp(position)
+            );
+        }
+        track.push_str("</trkseg></trk>");
+        self.tracks.push(track);
+        self
+    }
+}
+
+impl fmt::Display for Gpx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"<?xml version="1.0" encoding="UTF-8"?><gpx version="1.1" creator="{}" xmlns="http://www.topografix.com/GPX/1/1"><metadata><name>{}</name></metadata>"#,
+            encode_double_quoted_attribute(env!("CARGO_PKG_NAME")),
+            encode_text(&self.name)
+        )?;
+        for waypoint in &self.waypoints {
+            f.write_str(waypoint)?;
+        }
+        for track in &self.tracks {
+            f.write_str(track)?;
+        }
+        f.write_str("</gpx>")
+    }
+}
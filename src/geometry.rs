@@ -0,0 +1,256 @@
+//! Route shapes and stop locations as `geo-types` geometries and GeoJSON,
+//! ready to hand to mapping tools.
+//!
+//! The API gives coordinates as (latitude, longitude), geometries here are (x = longitude, y = latitude).
+
+use chrono::NaiveDate;
+use geo_types::{Coord, LineString, MultiLineString, Rect};
+use geojson::{Feature, FeatureCollection, Geometry, JsonObject};
+use serde::Serialize;
+
+use crate::{
+    helpers::coordinate_to_f64,
+    spatial::IndexedStop,
+    ty::{Coordinate, Geopath, RouteWithGeoPath, Stop},
+};
+
+pub(crate) fn coord((latitude, longitude): &(Coordinate, Coordinate)) -> Coord<f64> {
+    Coord {
+        x: coordinate_to_f64(*longitude),
+        y: coordinate_to_f64(*latitude),
+    }
+}
+
+/// Serialize a value into GeoJSON feature properties
+fn properties(value: &impl Serialize) -> Option<JsonObject> {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::Object(object)) => Some(object),
+        _ => None,
+    }
+}
+
+impl Geopath {
+    /// Each of the path's segments as a line
+    pub fn line_strings(&self) -> Vec<LineString<f64>> {
+        self.paths
+            .iter()
+            .map(|path| path.iter().map(coord).collect())
+            .collect()
+    }
+
+    /// A GeoJSON feature of the path, with its direction and validity as properties
+    pub fn to_geojson(&self) -> Feature {
+        let mut properties = JsonObject::new();
+        properties.insert("direction_id".into(), self.direction_id.0.into());
+        properties.insert("valid_from".into(), self.valid_from.to_string().into());
+        properties.insert("valid_to".into(), self.valid_to.to_string().into());
+
+        Feature {
+            geometry: Some(Geometry::from(&MultiLineString::from(self))),
+            properties: Some(properties),
+            ..Default::default()
+        }
+    }
+}
+
+/// Mean radius of the Earth, in metres
+pub(crate) const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Great circle distance between two coordinates, in metres
+pub fn haversine_metres(a: Coord<f64>, b: Coord<f64>) -> f64 {
+    let (lat_a, lat_b) = (a.y.to_radians(), b.y.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (b.x - a.x).to_radians();
+
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
+
+/// Project `point` onto the segment `start`-`end`, giving how far along the segment
+/// the nearest point is (0 to 1), the distance to it in metres and the segment's heading in degrees from north.
+/// Uses a flat projection around the segment, fine over the distances between path points
+pub(crate) fn project_onto_segment(
+    point: Coord<f64>,
+    start: Coord<f64>,
+    end: Coord<f64>,
+) -> (f64, f64, f64) {
+    let scale = start.y.to_radians().cos();
+    let project = |c: Coord<f64>| Coord {
+        x: (c.x - start.x).to_radians() * scale * EARTH_RADIUS,
+        y: (c.y - start.y).to_radians() * EARTH_RADIUS,
+    };
+    let (p, e) = (project(point), project(end));
+
+    let length = e.x * e.x + e.y * e.y;
+    let t = if length == 0.0 {
+        0.0
+    } else {
+        ((p.x * e.x + p.y * e.y) / length).clamp(0.0, 1.0)
+    };
+    let heading = e.x.atan2(e.y).to_degrees().rem_euclid(360.0);
+    (t, (p.x - t * e.x).hypot(p.y - t * e.y), heading)
+}
+
+/// Distance in metres from `point` to the segment `start`-`end`
+fn segment_distance_metres(point: Coord<f64>, start: Coord<f64>, end: Coord<f64>) -> f64 {
+    project_onto_segment(point, start, end).1
+}
+
+/// Douglas–Peucker, returning which points to keep
+fn douglas_peucker(points: &[Coord<f64>], tolerance: f64, keep: &mut [bool]) {
+    let [start, .., end] = points else {
+        return;
+    };
+
+    let furthest = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(i, point)| (i + 1, segment_distance_metres(*point, *start, *end)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+    if let Some((i, _)) = furthest.filter(|(_, distance)| *distance > tolerance) {
+        keep[i] = true;
+        douglas_peucker(&points[..=i], tolerance, &mut keep[..=i]);
+        douglas_peucker(&points[i..], tolerance, &mut keep[i..]);
+    }
+}
+
+impl Geopath {
+    /// Length of the path along the ground, in metres (haversine, summed over every segment)
+    pub fn length_metres(&self) -> f64 {
+        self.line_strings()
+            .iter()
+            .flat_map(|line| line.lines())
+            .map(|line| haversine_metres(line.start, line.end))
+            .sum()
+    }
+
+    /// The smallest rectangle containing every point of the path, None if it has no points
+    pub fn bbox(&self) -> Option<Rect<f64>> {
+        let mut coords = self.paths.iter().flatten().map(coord);
+        let first = coords.next()?;
+        let (min, max) = coords.fold((first, first), |(min, max), c| {
+            (
+                Coord {
+                    x: min.x.min(c.x),
+                    y: min.y.min(c.y),
+                },
+                Coord {
+                    x: max.x.max(c.x),
+                    y: max.y.max(c.y),
+                },
+            )
+        });
+        Some(Rect::new(min, max))
+    }
+
+    /// A copy of the path with points dropped (Douglas–Peucker) wherever
+    /// the shape stays within `tolerance` metres of the original.
+    /// The ends of each segment are always kept
+    pub fn simplify(&self, tolerance: f64) -> Geopath {
+        let paths = self
+            .paths
+            .iter()
+            .map(|path| {
+                let coords = path.iter().map(coord).collect::<Vec<_>>();
+                let mut keep = vec![false; path.len()];
+                if let (Some(first), Some(last)) = (keep.first_mut(), path.len().checked_sub(1)) {
+                    *first = true;
+                    keep[last] = true;
+                }
+                douglas_peucker(&coords, tolerance, &mut keep);
+
+                path.iter()
+                    .zip(keep)
+                    .filter_map(|(point, keep)| keep.then_some(*point))
+                    .collect()
+            })
+            .collect();
+
+        Geopath {
+            direction_id: self.direction_id,
+            valid_from: self.valid_from,
+            valid_to: self.valid_to,
+            paths,
+        }
+    }
+
+    /// Whether the path is in use on `date`
+    pub fn is_valid_on(&self, date: NaiveDate) -> bool {
+        (self.valid_from..=self.valid_to).contains(&date)
+    }
+}
+
+impl From<&Geopath> for MultiLineString<f64> {
+    fn from(geopath: &Geopath) -> Self {
+        MultiLineString::new(geopath.line_strings())
+    }
+}
+
+#[cfg(feature = "f64-geopath")]
+impl From<&crate::ty::GeopathF64> for MultiLineString<f64> {
+    fn from(geopath: &crate::ty::GeopathF64) -> Self {
+        geopath
+            .paths
+            .iter()
+            .map(|path| {
+                path.iter()
+                    .map(|(latitude, longitude)| Coord {
+                        x: longitude.0,
+                        y: latitude.0,
+                    })
+                    .collect::<LineString<f64>>()
+            })
+            .collect()
+    }
+}
+
+impl RouteWithGeoPath {
+    /// A GeoJSON feature per geopath, with the route's details added to each feature's properties.
+    /// Empty unless the route was requested with its geopath
+    pub fn to_geojson(&self) -> Vec<Feature> {
+        let route = properties(&self.route).unwrap_or_default();
+        self.geopath
+            .iter()
+            .flatten()
+            .map(|geopath| {
+                let mut feature = geopath.to_geojson();
+                feature
+                    .properties
+                    .get_or_insert_default()
+                    .extend(route.clone());
+                feature
+            })
+            .collect()
+    }
+
+    /// The route's shape in use on `date`.
+    /// Where the geopath covers several directions, this is the first one valid on the date
+    pub fn shape_for_date(&self, date: NaiveDate) -> Option<&Geopath> {
+        self.geopath.iter().flatten().find(|g| g.is_valid_on(date))
+    }
+}
+
+impl Stop {
+    /// A GeoJSON point feature for the stop, with its details as properties.
+    /// The geometry is left out if the stop has no location (see [`IndexedStop::location`])
+    pub fn to_geojson(&self) -> Feature {
+        Feature {
+            geometry: self.location().map(|point| Geometry::from(&point)),
+            properties: properties(self),
+            ..Default::default()
+        }
+    }
+}
+
+/// A GeoJSON feature collection of the routes' shapes
+pub fn routes_to_geojson<'a>(
+    routes: impl IntoIterator<Item = &'a RouteWithGeoPath>,
+) -> FeatureCollection {
+    FeatureCollection::new(routes.into_iter().flat_map(RouteWithGeoPath::to_geojson))
+}
+
+/// A GeoJSON feature collection of the stops' locations
+pub fn stops_to_geojson<'a>(stops: impl IntoIterator<Item = &'a Stop>) -> FeatureCollection {
+    FeatureCollection::new(stops.into_iter().map(Stop::to_geojson))
+}
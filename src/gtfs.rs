@@ -0,0 +1,791 @@
+//! Importing GTFS static timetables, as PTV publishes them, into the same types the API returns.
+//!
+//! The feed doesn't carry the API's numeric IDs, so the importer makes its own.
+//! Routes, directions and runs are numbered in the order they're read.
+//! Stops keep their GTFS `stop_id` when it's a number (PTV's are), and are numbered down from -1 otherwise.
+//! [`Route::gtfs_id`] is the feed's `route_id`, see [`Gtfs::route_by_gtfs_id`] for matching routes up with the API's.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    io::{self, BufReader, Cursor, Read, Seek},
+    path::Path,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use csv::StringRecord;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use zip::{ZipArchive, result::ZipError};
+
+use crate::{
+    helpers::parse_coordinate,
+    time::{MELBOURNE, MelbourneTime},
+    ty::{
+        Coordinate, Departure, Direction, DirectionId, Geopath, Route, RouteId, RouteType,
+        RouteWithGeoPath, RunId, RunRef, Stop, StopId,
+    },
+};
+
+#[derive(Deserialize)]
+struct RouteRow<'a> {
+    route_id: &'a str,
+    #[serde(default)]
+    route_short_name: &'a str,
+    #[serde(default)]
+    route_long_name: &'a str,
+    route_type: i32,
+}
+
+#[derive(Deserialize)]
+struct StopRow<'a> {
+    stop_id: &'a str,
+    #[serde(default)]
+    stop_name: &'a str,
+    #[serde(default)]
+    stop_desc: &'a str,
+    #[serde(default)]
+    stop_lat: &'a str,
+    #[serde(default)]
+    stop_lon: &'a str,
+    #[serde(default)]
+    platform_code: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct CalendarRow<'a> {
+    service_id: &'a str,
+    monday: u8,
+    tuesday: u8,
+    wednesday: u8,
+    thursday: u8,
+    friday: u8,
+    saturday: u8,
+    sunday: u8,
+    start_date: &'a str,
+    end_date: &'a str,
+}
+
+#[derive(Deserialize)]
+struct CalendarDateRow<'a> {
+    service_id: &'a str,
+    date: &'a str,
+    exception_type: u8,
+}
+
+#[derive(Deserialize)]
+struct ShapeRow<'a> {
+    shape_id: &'a str,
+    shape_pt_lat: &'a str,
+    shape_pt_lon: &'a str,
+    shape_pt_sequence: u32,
+}
+
+#[derive(Deserialize)]
+struct TripRow<'a> {
+    route_id: &'a str,
+    service_id: &'a str,
+    trip_id: &'a str,
+    #[serde(default)]
+    trip_headsign: &'a str,
+    #[serde(default)]
+    direction_id: Option<u8>,
+    #[serde(default)]
+    shape_id: Option<&'a str>,
+    #[serde(default)]
+    wheelchair_accessible: Option<u8>,
+}
+
+#[derive(Deserialize)]
+struct StopTimeRow<'a> {
+    trip_id: &'a str,
+    #[serde(default)]
+    arrival_time: &'a str,
+    #[serde(default)]
+    departure_time: &'a str,
+    stop_id: &'a str,
+    stop_sequence: i32,
+    #[serde(default)]
+    pickup_type: Option<u8>,
+    #[serde(default)]
+    drop_off_type: Option<u8>,
+}
+
+/// The days a service (calendar.txt and calendar_dates.txt) runs on
+#[derive(Debug, Clone, Default)]
+struct Service {
+    /// Monday first
+    days: [bool; 7],
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+    added: BTreeSet<NaiveDate>,
+    removed: BTreeSet<NaiveDate>,
+}
+
+impl Service {
+    fn runs_on(&self, date: NaiveDate) -> bool {
+        if self.removed.contains(&date) {
+            return false;
+        }
+        self.added.contains(&date)
+            || self
+                .start
+                .zip(self.end)
+                .is_some_and(|(start, end)| (start..=end).contains(&date))
+                && self.days[date.weekday().num_days_from_monday() as usize]
+    }
+
+    /// First and last days the service could run
+    fn validity(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let first = self
+            .start
+            .into_iter()
+            .chain(self.added.first().copied())
+            .min()?;
+        let last = self
+            .end
+            .into_iter()
+            .chain(self.added.last().copied())
+            .max()?;
+        Some((first, last))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct StopTime {
+    stop_id: StopId,
+    sequence: i32,
+    /// Seconds from the start of the service day
+    arrival: Option<i64>,
+    departure: Option<i64>,
+    pickup_type: u8,
+    drop_off_type: u8,
+}
+
+#[derive(Debug, Clone)]
+struct Trip {
+    run_id: RunId,
+    run_ref: RunRef,
+    route_id: RouteId,
+    direction_id: DirectionId,
+    service: usize,
+    wheelchair_accessible: bool,
+    stop_times: Vec<StopTime>,
+}
+
+/// A GTFS static feed, read into API types
+#[derive(Debug, Default)]
+pub struct Gtfs {
+    /// Stops at least one trip calls at
+    pub stops: BTreeMap<StopId, Stop>,
+    /// Routes, each with a geopath per direction made up of the shapes its trips follow
+    pub routes: BTreeMap<RouteId, RouteWithGeoPath>,
+    /// A direction per route and GTFS `direction_id`, named after the trips' headsign
+    pub directions: BTreeMap<DirectionId, Direction>,
+    stop_ids: HashMap<String, StopId>,
+    gtfs_stop_ids: BTreeMap<StopId, String>,
+    platforms: BTreeMap<StopId, String>,
+    services: Vec<Service>,
+    trips: Vec<Trip>,
+    /// Trips by route, stop and time from the start of the service day, for [`Gtfs::find_trip`]
+    trip_index: BTreeMap<(RouteId, StopId, i64), Vec<usize>>,
+}
+
+/// Where the files of a feed are read from
+trait FeedFiles {
+    /// Call `f` with the contents of file `name`. False if the feed doesn't have it
+    fn with_file(
+        &mut self,
+        name: &str,
+        f: &mut dyn FnMut(&mut dyn Read) -> Result<()>,
+    ) -> Result<bool>;
+}
+
+/// A directory of extracted files
+struct Directory<'a>(&'a Path);
+
+impl FeedFiles for Directory<'_> {
+    fn with_file(
+        &mut self,
+        name: &str,
+        f: &mut dyn FnMut(&mut dyn Read) -> Result<()>,
+    ) -> Result<bool> {
+        match fs::File::open(self.0.join(name)) {
+            Ok(mut file) => f(&mut file).map(|()| true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl<R: Read + Seek> FeedFiles for ZipArchive<R> {
+    fn with_file(
+        &mut self,
+        name: &str,
+        f: &mut dyn FnMut(&mut dyn Read) -> Result<()>,
+    ) -> Result<bool> {
+        match self.by_name(name) {
+            Ok(mut file) => f(&mut file).map(|()| true),
+            Err(ZipError::FileNotFound) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Stream the records of a CSV file in the feed through `f`, along with the file's headers.
+/// A single record is reused throughout, so rows borrowing from it cost no allocations.
+/// False if the feed doesn't have the file
+fn each_record(
+    files: &mut impl FeedFiles,
+    name: &str,
+    mut f: impl FnMut(&StringRecord, &StringRecord) -> Result<()>,
+) -> Result<bool> {
+    files
+        .with_file(name, &mut |file| {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_reader(file);
+            let headers = reader.headers()?.clone();
+            let mut record = StringRecord::new();
+            while reader.read_record(&mut record)? {
+                f(&record, &headers)?;
+            }
+            Ok(())
+        })
+        .with_context(|| format!("parsing {name}"))
+}
+
+/// [`each_record`] for a file the feed has to have
+fn each_required_record(
+    files: &mut impl FeedFiles,
+    name: &str,
+    f: impl FnMut(&StringRecord, &StringRecord) -> Result<()>,
+) -> Result<()> {
+    match each_record(files, name, f)? {
+        true => Ok(()),
+        false => bail!("feed has no {name}"),
+    }
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y%m%d").with_context(|| format!("invalid date '{date}'"))
+}
+
+/// Seconds from the start of the service day, "25:10:00" being ten past one the next morning.
+/// None if the stop isn't timed
+fn parse_time(time: &str) -> Result<Option<i64>> {
+    if time.is_empty() {
+        return Ok(None);
+    }
+    let mut parts = time.split(':').map(str::parse::<i64>);
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(hours)), Some(Ok(minutes)), Some(Ok(seconds)), None)
+            if hours >= 0 && (0..60).contains(&minutes) && (0..60).contains(&seconds) =>
+        {
+            Ok(Some(hours * 3600 + minutes * 60 + seconds))
+        }
+        _ => bail!("invalid time '{time}'"),
+    }
+}
+
+/// Seconds from the start of the service day as a GTFS time, past 24:00:00 after midnight
+pub(crate) fn format_time(seconds: i64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// When a service day's times count from. That's twelve hours before noon,
+/// which is midnight unless the clocks change that night
+pub(crate) fn service_day_start(date: NaiveDate) -> Option<DateTime<Utc>> {
+    let noon = MELBOURNE
+        .with_ymd_and_hms(date.year(), date.month(), date.day(), 12, 0, 0)
+        .single()?;
+    Some(noon.with_timezone(&Utc) - Duration::hours(12))
+}
+
+impl RouteType {
+    /// The closest route type to a GTFS `route_type`, basic or extended.
+    /// Rail is [`RouteType::Train`] unless it's regional (extended types 102, 103 and 106),
+    /// and coaches are [`RouteType::VLine`], who run them here. Anything else is `Other(-1)`
+    pub fn from_gtfs(route_type: i32) -> RouteType {
+        match route_type {
+            102 | 103 | 106 | 200..=299 => RouteType::VLine,
+            705 => RouteType::NightBus,
+            1 | 2 | 12 | 100..=199 | 400..=499 => RouteType::Train,
+            0 | 5 | 900..=999 => RouteType::Tram,
+            3 | 11 | 700..=899 => RouteType::Bus,
+            _ => RouteType::Other(-1),
+        }
+    }
+}
+
+/// PTV prefix route IDs with the number of the feed they're published in (e.g. "2-ALM", a metropolitan train),
+/// which tells V/Line trains from Metro ones where `route_type` can't
+fn route_type(route_id: &str, route_type: i32) -> RouteType {
+    match route_id.split_once('-').map(|(mode, _)| mode) {
+        Some("1" | "5") => RouteType::VLine,
+        Some("2") => RouteType::Train,
+        Some("3") => RouteType::Tram,
+        Some("4" | "6" | "7" | "11") => RouteType::Bus,
+        Some("8") => RouteType::NightBus,
+        _ => RouteType::from_gtfs(route_type),
+    }
+}
+
+impl Gtfs {
+    /// Read a GTFS zip, or PTV's zip of zips (one per mode), which are merged together
+    pub fn from_zip(reader: impl Read + Seek) -> Result<Gtfs> {
+        let mut gtfs = Gtfs::default();
+        gtfs.add_zip(reader)?;
+        Ok(gtfs)
+    }
+
+    /// Read a GTFS zip (see [`Gtfs::from_zip`]), or a directory of the extracted files
+    pub fn open(path: impl AsRef<Path>) -> Result<Gtfs> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            let file =
+                fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+            return Gtfs::from_zip(BufReader::new(file));
+        }
+
+        let mut gtfs = Gtfs::default();
+        gtfs.add_feed(&mut Directory(path))?;
+        Ok(gtfs)
+    }
+
+    fn add_zip(&mut self, reader: impl Read + Seek) -> Result<()> {
+        let mut archive = ZipArchive::new(reader)?;
+        if archive.index_for_name("stops.txt").is_some() {
+            return self.add_feed(&mut archive);
+        }
+
+        let mut nested = archive
+            .file_names()
+            .filter_map(|name| name.ok())
+            .filter(|name| name.ends_with(".zip"))
+            .map(|name| name.into_owned())
+            .collect::<Vec<_>>();
+        if nested.is_empty() {
+            bail!("zip has no stops.txt, or feeds inside it");
+        }
+        nested.sort();
+        for name in nested {
+            // Zips need seeking, so the inner one is read into memory
+            let mut data = Vec::new();
+            archive.by_name(&name)?.read_to_end(&mut data)?;
+            self.add_zip(Cursor::new(data))
+                .with_context(|| format!("reading {name}"))?;
+        }
+        Ok(())
+    }
+
+    /// The stop ID for a GTFS `stop_id`, making one up if it isn't a number or is already taken
+    fn assign_stop_id(&mut self, gtfs_id: &str) -> StopId {
+        if let Some(id) = self.stop_ids.get(gtfs_id) {
+            return *id;
+        }
+        let id = match gtfs_id.parse() {
+            Ok(id) if id >= 0 && !self.gtfs_stop_ids.contains_key(&StopId(id)) => StopId(id),
+            _ => StopId(
+                self.gtfs_stop_ids
+                    .first_key_value()
+                    .map_or(0, |(id, _)| id.0.min(0))
+                    - 1,
+            ),
+        };
+        self.stop_ids.insert(gtfs_id.to_owned(), id);
+        self.gtfs_stop_ids.insert(id, gtfs_id.to_owned());
+        id
+    }
+
+    /// Read one feed
+    fn add_feed(&mut self, files: &mut impl FeedFiles) -> Result<()> {
+        // IDs are only unique within a feed, other than stops
+        let mut routes = HashMap::new();
+        each_required_record(files, "routes.txt", |record, headers| {
+            let row: RouteRow = record.deserialize(Some(headers))?;
+            let id = RouteId(self.routes.len() as i32 + 1);
+            let (name, number) = match row.route_long_name.is_empty() {
+                true => (row.route_short_name, ""),
+                false => (row.route_long_name, row.route_short_name),
+            };
+            self.routes.insert(
+                id,
+                RouteWithGeoPath {
+                    route: Route {
+                        route_type: route_type(row.route_id, row.route_type),
+                        id,
+                        name: name.to_owned(),
+                        number: number.to_owned(),
+                        gtfs_id: row.route_id.to_owned(),
+                    },
+                    geopath: None,
+                },
+            );
+            routes.insert(row.route_id.to_owned(), id);
+            Ok(())
+        })?;
+
+        // Stops with their platform, until a trip calling at them gives them a route type.
+        // Stops without a location are left out
+        let mut stops = BTreeMap::new();
+        each_required_record(files, "stops.txt", |record, headers| {
+            let row: StopRow = record.deserialize(Some(headers))?;
+            let id = self.assign_stop_id(row.stop_id);
+            let (Ok(latitude), Ok(longitude)) = (
+                row.stop_lat.parse::<Decimal>(),
+                row.stop_lon.parse::<Decimal>(),
+            ) else {
+                return Ok(());
+            };
+            let stop = Stop {
+                distance: Decimal::ZERO,
+                suburb: String::new(),
+                name: row.stop_name.to_owned(),
+                id,
+                route_type: RouteType::Other(-1),
+                latitude,
+                longitude,
+                landmark: row.stop_desc.to_owned(),
+                sequence: 0,
+            };
+            let platform = row.platform_code.filter(|p| !p.is_empty());
+            stops.insert(id, (stop, platform.map(str::to_owned)));
+            Ok(())
+        })?;
+
+        let mut services = HashMap::new();
+        let mut service = |id: &str, all: &mut Vec<Service>| match services.get(id) {
+            Some(index) => *index,
+            None => {
+                all.push(Service::default());
+                services.insert(id.to_owned(), all.len() - 1);
+                all.len() - 1
+            }
+        };
+        each_record(files, "calendar.txt", |record, headers| {
+            let row: CalendarRow = record.deserialize(Some(headers))?;
+            let index = service(row.service_id, &mut self.services);
+            let days = [
+                row.monday,
+                row.tuesday,
+                row.wednesday,
+                row.thursday,
+                row.friday,
+                row.saturday,
+                row.sunday,
+            ];
+            let service = &mut self.services[index];
+            service.days = days.map(|day| day == 1);
+            service.start = Some(parse_date(row.start_date)?);
+            service.end = Some(parse_date(row.end_date)?);
+            Ok(())
+        })?;
+        each_record(files, "calendar_dates.txt", |record, headers| {
+            let row: CalendarDateRow = record.deserialize(Some(headers))?;
+            let date = parse_date(row.date)?;
+            let index = service(row.service_id, &mut self.services);
+            let service = &mut self.services[index];
+            match row.exception_type {
+                1 => service.added.insert(date),
+                2 => service.removed.insert(date),
+                other => bail!("invalid exception_type {other}"),
+            };
+            Ok(())
+        })?;
+
+        let mut shapes = HashMap::<String, Vec<(u32, (Coordinate, Coordinate))>>::new();
+        each_record(files, "shapes.txt", |record, headers| {
+            let row: ShapeRow = record.deserialize(Some(headers))?;
+            let point = (
+                parse_coordinate(row.shape_pt_lat).map_err(|e| anyhow!(e))?,
+                parse_coordinate(row.shape_pt_lon).map_err(|e| anyhow!(e))?,
+            );
+            let point = (row.shape_pt_sequence, point);
+            match shapes.get_mut(row.shape_id) {
+                Some(shape) => shape.push(point),
+                None => {
+                    shapes.insert(row.shape_id.to_owned(), vec![point]);
+                }
+            }
+            Ok(())
+        })?;
+        for shape in shapes.values_mut() {
+            shape.sort_by_key(|(sequence, _)| *sequence);
+        }
+
+        let first_trip = self.trips.len();
+        let mut trips = HashMap::new();
+        let mut directions = BTreeMap::new();
+        // The shapes followed in each direction of a route, and when they're followed
+        let mut shapes_used = BTreeMap::<(RouteId, DirectionId), (Vec<String>, Vec<usize>)>::new();
+        each_required_record(files, "trips.txt", |record, headers| {
+            let row: TripRow = record.deserialize(Some(headers))?;
+            let route_id = *routes.get(row.route_id).with_context(|| {
+                format!("trip {} has unknown route {}", row.trip_id, row.route_id)
+            })?;
+            let service = *services.get(row.service_id).with_context(|| {
+                format!(
+                    "trip {} has unknown service {}",
+                    row.trip_id, row.service_id
+                )
+            })?;
+            let direction_id = *directions
+                .entry((route_id, row.direction_id.unwrap_or(0)))
+                .or_insert_with(|| {
+                    let id = DirectionId(self.directions.len() as i32 + 1);
+                    self.directions.insert(
+                        id,
+                        Direction {
+                            id,
+                            name: row.trip_headsign.to_owned(),
+                            route_id,
+                            route_type: self.routes[&route_id].route.route_type,
+                        },
+                    );
+                    id
+                });
+
+            let (shape_ids, used_by) = shapes_used.entry((route_id, direction_id)).or_default();
+            if let Some(shape_id) = row.shape_id
+                && !shape_ids.iter().any(|id| id == shape_id)
+            {
+                shape_ids.push(shape_id.to_owned());
+            }
+            used_by.push(service);
+
+            trips.insert(row.trip_id.to_owned(), self.trips.len());
+            self.trips.push(Trip {
+                run_id: RunId(self.trips.len() as i32 + 1),
+                run_ref: RunRef::from(row.trip_id),
+                route_id,
+                direction_id,
+                service,
+                wheelchair_accessible: row.wheelchair_accessible == Some(1),
+                stop_times: Vec::new(),
+            });
+            Ok(())
+        })?;
+
+        each_required_record(files, "stop_times.txt", |record, headers| {
+            let row: StopTimeRow = record.deserialize(Some(headers))?;
+            let trip = *trips
+                .get(row.trip_id)
+                .with_context(|| format!("stop time for unknown trip {}", row.trip_id))?;
+            let stop_id = *self.stop_ids.get(row.stop_id).with_context(|| {
+                format!("trip {} calls at unknown stop {}", row.trip_id, row.stop_id)
+            })?;
+            self.trips[trip].stop_times.push(StopTime {
+                stop_id,
+                sequence: row.stop_sequence,
+                arrival: parse_time(row.arrival_time)?,
+                departure: parse_time(row.departure_time)?,
+                pickup_type: row.pickup_type.unwrap_or(0),
+                drop_off_type: row.drop_off_type.unwrap_or(0),
+            });
+            Ok(())
+        })?;
+
+        for (index, trip) in self.trips.iter_mut().enumerate().skip(first_trip) {
+            trip.stop_times.sort_by_key(|s| s.sequence);
+            for stop_time in &trip.stop_times {
+                if let Some(time) = stop_time.departure.or(stop_time.arrival) {
+                    self.trip_index
+                        .entry((trip.route_id, stop_time.stop_id, time))
+                        .or_default()
+                        .push(index);
+                }
+            }
+
+            // Stops take the route type of the first trip calling at them
+            let route_type = self.routes[&trip.route_id].route.route_type;
+            for stop_time in &trip.stop_times {
+                if self.stops.contains_key(&stop_time.stop_id) {
+                    continue;
+                }
+                let Some((mut stop, platform)) = stops.remove(&stop_time.stop_id) else {
+                    continue;
+                };
+                stop.route_type = route_type;
+                if let Some(platform) = platform {
+                    self.platforms.insert(stop_time.stop_id, platform);
+                }
+                self.stops.insert(stop_time.stop_id, stop);
+            }
+
+            // Directions without a headsign are named after where the trips end
+            let direction = self.directions.get_mut(&trip.direction_id);
+            if let Some(direction) = direction.filter(|d| d.name.is_empty())
+                && let Some(stop) = trip
+                    .stop_times
+                    .last()
+                    .and_then(|s| self.stops.get(&s.stop_id))
+            {
+                direction.name = stop.name.clone();
+            }
+        }
+
+        for ((route_id, direction_id), (shape_ids, used_by)) in shapes_used {
+            let validity = used_by
+                .iter()
+                .filter_map(|&service| self.services[service].validity())
+                .reduce(|(from, to), (f, t)| (from.min(f), to.max(t)));
+            let paths = shape_ids
+                .iter()
+                .filter_map(|id| shapes.get(id))
+                .map(|shape| shape.iter().map(|(_, point)| *point).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let Some((valid_from, valid_to)) = validity.filter(|_| !paths.is_empty()) else {
+                continue;
+            };
+            if let Some(route) = self.routes.get_mut(&route_id) {
+                route.geopath.get_or_insert_with(Vec::new).push(Geopath {
+                    direction_id,
+                    valid_from,
+                    valid_to,
+                    paths,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The route with GTFS `route_id` `gtfs_id`.
+    /// The API's [`Route::gtfs_id`] (e.g. "2-ALM") also matches feed IDs it's followed by a suffix in (e.g. "2-ALM-mjp-1")
+    pub fn route_by_gtfs_id(&self, gtfs_id: &str) -> Option<&RouteWithGeoPath> {
+        self.routes
+            .values()
+            .find(|r| r.route.gtfs_id == gtfs_id)
+            .or_else(|| {
+                self.routes.values().find(|r| {
+                    r.route
+                        .gtfs_id
+                        .strip_prefix(gtfs_id)
+                        .is_some_and(|rest| rest.starts_with('-'))
+                })
+            })
+    }
+
+    /// The stop ID given to GTFS `stop_id` `gtfs_id`
+    pub fn stop_id(&self, gtfs_id: &str) -> Option<StopId> {
+        self.stop_ids.get(gtfs_id).copied()
+    }
+
+    /// The GTFS `stop_id` of a stop
+    pub fn gtfs_stop_id(&self, stop_id: StopId) -> Option<&str> {
+        self.gtfs_stop_ids.get(&stop_id).map(String::as_str)
+    }
+
+    /// Directions of travel on a route
+    pub fn route_directions(&self, route_id: RouteId) -> impl Iterator<Item = &Direction> {
+        self.directions
+            .values()
+            .filter(move |d| d.route_id == route_id)
+    }
+
+    /// The feed's trip for a departure from the API: one on the same route, leaving the same stop at the same scheduled time.
+    /// `route` is matched by [`Route::gtfs_id`] (see [`Gtfs::route_by_gtfs_id`]),
+    /// and the departure's stop ID is taken to be a GTFS `stop_id`, as PTV's mostly are
+    pub fn find_trip(&self, route: &Route, departure: &Departure) -> Option<&RunRef> {
+        self.find_trip_date(route, departure)
+            .map(|(run_ref, _)| run_ref)
+    }
+
+    /// [`Gtfs::find_trip`], along with the service day the trip runs on
+    pub fn find_trip_date(
+        &self,
+        route: &Route,
+        departure: &Departure,
+    ) -> Option<(&RunRef, NaiveDate)> {
+        let route_id = self.route_by_gtfs_id(&route.gtfs_id)?.route.id;
+        let stop_id = self.stop_id(&departure.stop_id.to_string())?;
+        let scheduled = departure.scheduled_departure?;
+
+        // Trips after midnight belong to the service day before
+        let date = scheduled.to_melbourne().date_naive();
+        [Some(date), date.pred_opt()]
+            .into_iter()
+            .flatten()
+            .find_map(|date| {
+                let time = (scheduled - service_day_start(date)?).num_seconds();
+                let trip = self
+                    .trip_index
+                    .get(&(route_id, stop_id, time))?
+                    .iter()
+                    .map(|&index| &self.trips[index])
+                    .find(|trip| self.services[trip.service].runs_on(date))?;
+                Some((&trip.run_ref, date))
+            })
+    }
+
+    /// Scheduled departures of every trip running on the service day `date`, in time order.
+    /// As in GTFS, a service day's trips can run on past midnight into the next morning
+    pub fn departures(&self, date: NaiveDate) -> Vec<Departure> {
+        self.scheduled(date, |_| true)
+    }
+
+    /// Scheduled departures from a stop on the service day `date`, in time order
+    pub fn stop_departures(&self, stop_id: StopId, date: NaiveDate) -> Vec<Departure> {
+        self.scheduled(date, |stop_time| stop_time.stop_id == stop_id)
+    }
+
+    fn scheduled(&self, date: NaiveDate, include: impl Fn(&StopTime) -> bool) -> Vec<Departure> {
+        let Some(start) = service_day_start(date) else {
+            return Vec::new();
+        };
+
+        let mut departures = self
+            .trips
+            .iter()
+            .filter(|trip| self.services[trip.service].runs_on(date))
+            .flat_map(|trip| {
+                trip.stop_times
+                    .iter()
+                    .filter(|stop_time| include(stop_time))
+                    .filter_map(move |stop_time| {
+                        let time = stop_time.departure.or(stop_time.arrival)?;
+                        Some(self.departure(trip, stop_time, start + Duration::seconds(time)))
+                    })
+            })
+            .collect::<Vec<_>>();
+        departures.sort_by_key(|d| d.scheduled_departure);
+        departures
+    }
+
+    fn departure(&self, trip: &Trip, stop_time: &StopTime, time: DateTime<Utc>) -> Departure {
+        let mut flags = Vec::new();
+        if stop_time.pickup_type == 1 {
+            flags.push("DOO");
+        }
+        if stop_time.drop_off_type == 1 {
+            flags.push("PUO");
+        }
+        if trip.wheelchair_accessible {
+            flags.push("S_WCA");
+        }
+        let Ok(flags) = flags.join("_").parse();
+
+        Departure {
+            stop_id: stop_time.stop_id,
+            route_id: trip.route_id,
+            run_id: trip.run_id,
+            run_ref: trip.run_ref.clone(),
+            direction_id: trip.direction_id,
+            disruption_ids: Vec::new(),
+            scheduled_departure: Some(time),
+            estimated_departure: None,
+            at_platform: false,
+            platform_number: self.platforms.get(&stop_time.stop_id).cloned(),
+            flags,
+            departure_sequence: stop_time.sequence,
+            skipped_stops: None,
+        }
+    }
+}
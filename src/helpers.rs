@@ -1,9 +1,12 @@
-use chrono::NaiveDateTime;
-use itertools::Itertools;
+use chrono::{DateTime, NaiveDateTime, NaiveTime, SecondsFormat, Utc};
 use rust_decimal::Decimal;
+use std::marker::PhantomData;
+
 use serde::{Deserialize, Deserializer, Serialize, Serializer, de::SeqAccess, ser};
 
-use crate::DisruptionModes;
+#[cfg(feature = "f64-geopath")]
+use crate::Degrees;
+use crate::{Coordinate, DisruptionModes, RequestDate};
 
 pub fn clean(s: String) -> String {
     let mut s = s;
@@ -42,27 +45,37 @@ pub fn to_query<T: Serialize>(s: T) -> String {
         .join("&")
 }
 
-pub fn de_iso_8601<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
+pub fn de_iso_8601<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
 where
     D: Deserializer<'de>,
 {
     let s: String = String::deserialize(deserializer)?;
     NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%S")
+        .map(|date| date.and_utc())
         .map_err(|e| serde::de::Error::custom(format!("Error deser iso_8601 '{s}': {e:?}")))
 }
 
-pub fn ser_iso_8601<S>(date: &Option<NaiveDateTime>, serializer: S) -> Result<S::Ok, S::Error>
+/// Any [`DateTime`] or [`RequestDate`], sent as UTC
+pub fn ser_iso_8601<S, D>(date: &Option<D>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
+    D: Clone + Into<RequestDate>,
 {
     match date {
-        Some(date) => serializer.serialize_str(&date.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        Some(date) => serializer.serialize_str(
+            &date
+                .clone()
+                .into()
+                .0
+                .format("%Y-%m-%dT%H:%M:%S")
+                .to_string(),
+        ),
         None => serializer.serialize_none(),
     }
 }
 
 /// 24 hour clock format (HH:MM:SS) AEDT/AEST
-pub fn de_service_time<'de, D>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error>
+pub fn de_service_time<'de, D>(deserializer: D) -> Result<Option<NaiveTime>, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -72,85 +85,137 @@ where
             if s.is_empty() {
                 Ok(None)
             } else {
-                Ok(Some(
-                    NaiveDateTime::parse_from_str(&s, "%H:%M:%S").map_err(|e| {
-                        serde::de::Error::custom(format!("Error deser service_time '{s}': {e:?}"))
-                    })?,
-                ))
+                Ok(Some(NaiveTime::parse_from_str(&s, "%H:%M:%S").map_err(
+                    |e| serde::de::Error::custom(format!("Error deser service_time '{s}': {e:?}")),
+                )?))
             }
         }
         None => Ok(None),
     }
 }
 
-// yyyy-MM-dd HH:mm
-pub fn ser_touch_utc<S>(date: &Option<NaiveDateTime>, serializer: S) -> Result<S::Ok, S::Error>
+// yyyy-MM-dd HH:mm, sent as UTC
+pub fn ser_touch_utc<S, D>(date: &Option<D>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
+    D: Clone + Into<RequestDate>,
 {
     match date {
-        Some(date) => serializer.serialize_str(&date.format("%Y-%m-%d %H:%M").to_string()),
+        Some(date) => {
+            serializer.serialize_str(&date.clone().into().0.format("%Y-%m-%d %H:%M").to_string())
+        }
         None => serializer.serialize_none(),
     }
 }
 
-pub fn de_rfc3339<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
+fn parse_rfc3339<E: serde::de::Error>(s: &str) -> Result<DateTime<Utc>, E> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|e| E::custom(format!("Error deser rfc3339 '{s}': {e:?}")))
+}
+
+pub fn de_rfc3339<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
 where
     D: Deserializer<'de>,
 {
     let s: String = String::deserialize(deserializer)?;
-    NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%S%.3fZ")
-        .map_err(|e| serde::de::Error::custom(format!("Error deser rfc3339 '{s}': {e:?}")))
+    parse_rfc3339(&s)
 }
 
-pub fn ser_rfc3339<S>(date: &chrono::NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error>
+pub fn ser_rfc3339<S>(date: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let s = date.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
-    serializer.serialize_str(&s)
+    serializer.serialize_str(&date.to_rfc3339_opts(SecondsFormat::Millis, true))
 }
 
-pub fn opt_de_rfc3339<'de, D>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error>
+pub fn opt_de_rfc3339<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
 where
     D: Deserializer<'de>,
 {
     let s: Option<String> = Option::deserialize(deserializer)?;
     match s {
-        Some(s) => Ok(Some(
-            NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%S%.3fZ").map_err(|e| {
-                serde::de::Error::custom(format!("Error deser rfc3339 '{s}': {e:?}"))
-            })?,
-        )),
+        Some(s) => Ok(Some(parse_rfc3339(&s)?)),
         None => Ok(None),
     }
 }
-pub fn opt_ser_rfc3339<S>(
-    date: &Option<chrono::NaiveDateTime>,
-    serializer: S,
-) -> Result<S::Ok, S::Error>
+pub fn opt_ser_rfc3339<S>(date: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
     match date {
-        Some(date) => {
-            let s = date.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
-            serializer.serialize_str(&s)
-        }
+        Some(date) => serializer.serialize_str(&date.to_rfc3339_opts(SecondsFormat::Millis, true)),
         None => serializer.serialize_none(),
     }
 }
 
-pub fn deserialize_path<'de, D>(deserializer: D) -> Result<Vec<Vec<(Decimal, Decimal)>>, D::Error>
+/// The API isn't consistent about timestamps. Depending on the endpoint they come with or without
+/// fractional seconds (up to 7 digits), with a "Z" or "+10:00" style offset, or with no offset at all.
+/// Timestamps without an offset are assumed to be UTC.
+fn parse_timestamp<E: serde::de::Error>(s: &str) -> Result<DateTime<Utc>, E> {
+    let s = s.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(s) {
+        return Ok(date.with_timezone(&Utc));
+    }
+
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .map(|date| date.and_utc())
+        .ok_or_else(|| E::custom(format!("Error deser timestamp '{s}'")))
+}
+
+pub fn de_timestamp<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = String::deserialize(deserializer)?;
+    parse_timestamp(&s)
+}
+
+/// Blank strings are treated as None
+pub fn opt_de_timestamp<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: Option<String> = Option::deserialize(deserializer)?;
+    match s {
+        Some(s) if !s.trim().is_empty() => Ok(Some(parse_timestamp(&s)?)),
+        _ => Ok(None),
+    }
+}
+
+pub fn deserialize_path<'de, D>(
+    deserializer: D,
+) -> Result<Vec<Vec<(Coordinate, Coordinate)>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_paths(deserializer)
+}
+
+/// [`deserialize_path`], parsing the coordinates straight to `f64`
+#[cfg(feature = "f64-geopath")]
+pub fn deserialize_path_f64<'de, D>(
+    deserializer: D,
+) -> Result<Vec<Vec<(Degrees, Degrees)>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_paths(deserializer)
+}
+
+fn deserialize_paths<'de, D, C>(deserializer: D) -> Result<Vec<Vec<(C, C)>>, D::Error>
 where
     D: serde::Deserializer<'de>,
+    C: ParseCoordinate,
 {
-    struct PathVisitor;
-    impl<'de> serde::de::Visitor<'de> for PathVisitor {
-        type Value = Vec<Vec<(Decimal, Decimal)>>;
+    struct PathVisitor<C>(PhantomData<C>);
+    impl<'de, C: ParseCoordinate> serde::de::Visitor<'de> for PathVisitor<C> {
+        type Value = Vec<Vec<(C, C)>>;
 
         fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-            formatter.write_str("a list of lists of tuples of Decimal")
+            formatter.write_str("a list of paths of space separated \"latitude, longitude\" pairs")
         }
         // primarily deserializing [
         //  "-37.8683203000288, 145.079655599963 -37.8655618999753, 145.080159200029 -37.8643671999759, 145.080394800045 -37.8637750999515, 145.080557999986 -37.8635422999975, 145.080654900021 -37.8582984000176, 145.082927900024 -37.8579842999768, 145.083004200024 -37.8572559000092, 145.083102600027 -37.8562641000235, 145.083071499962 -37.8556125999982, 145.082906500037 -37.8554588999607, 145.082876400034 -37.8545596999964, 145.082445099949 -37.8437658999819, 145.075417500015 -37.8425731999696, 145.074675700034 -37.8364900000393, 145.070811399995 -37.8348502000055, 145.069729200021 -37.8342419999945, 145.069460999968 -37.8338166999764, 145.069358400021 -37.8330147999865, 145.069368000002 -37.832817199959, 145.069407200039 -37.832754099991, 145.069408900034 -37.8310293000002, 145.069737900034 -37.8283767000385, 145.070102400024 -37.8280790999898, 145.070087399984 -37.8277532999815, 145.070005000044 -37.8271535999531, 145.069702499947 -37.8267788000005, 145.06938279996 -37.8265577999701, 145.069093199963 -37.8263718999837, 145.068745799971 -37.8261712000388, 145.068046599958 -37.8261099999648, 145.067616499972 -37.8261036999724, 145.06723039997 -37.8262540999595, 145.065965300042 -37.8262642999546, 145.0654879 -37.8262822000221, 145.06493069998 -37.8263466999918, 145.06337249997 -37.8264930000373, 145.061857600011 -37.8265761000108, 145.058696899958",
@@ -162,47 +227,154 @@ where
             A: SeqAccess<'de>,
         {
             let mut paths = Vec::new();
-            while let Some(path) = seq.next_element::<String>()? {
-                // take a
-                // "-37.8201561999773, 144.98079590002 -37.8177107999522, 144.97693200003 -37.8171955999625, 144.97632120001 -37.8166556000173, 144.975836200011 -37.8162349999771, 144.975484099993 -37.8159246000112, 144.975254099958 -37.8156684999692, 144.975033899985 -37.8155869000169, 144.975002000052 -37.8152870999753, 144.974862500026 -37.8150237000143, 144.974744800009 -37.8087737999705, 144.97184869999 -37.8086363000065, 144.971716200048 -37.8085259000299, 144.971582900021 -37.8083964000279, 144.971393299951 -37.8082289999986, 144.971091199971 -37.808172399957, 144.970945099968 -37.8080764999841, 144.970606999995 -37.808025400012, 144.970256300053 -37.8080015000372, 144.969916199985 -37.8079953999606, 144.96956419998 -37.808034999982, 144.969245100018 -37.8080848999907, 144.969005199993 -37.8099386999852, 144.962593500014 -37.8103137999581, 144.961356399982 -37.8117517000372, 144.956443799957 -37.8133308999864, 144.950868300016 -37.8134615000047, 144.950603399969 -37.8135931000251, 144.950395299996 -37.8138054999956, 144.950173599965 -37.81398329996, 144.950032400054 -37.81418889995, 144.949935799977 -37.8145199999943, 144.949801699966 -37.8147624000128, 144.949749500022 -37.8149150999922, 144.949722600005 -37.8151223000106, 144.949716899987 -37.8153748999829, 144.949732599976 -37.8154472999846, 144.949753299957 -37.8156823000166, 144.949792299978 -37.8161265999766, 144.949950400014 -37.8163266000045, 144.950047100001 -37.8172413000239, 144.950828299953 -37.8174253999886, 144.951050400047 -37.8194719000155, 144.952652399974 -37.820146400037, 144.953088200015 -37.8203663000234, 144.953297999947 -37.820983800037, 144.954087499969 -37.8211138999842, 144.95431110001 -37.8212360999886, 144.954603099955 -37.8213497000187, 144.954918100029 -37.8214091000209, 144.955223199949 -37.8214777999948, 144.955550700002 -37.8214793999859, 144.955641600043 -37.8214930000136, 144.95590249995 -37.8214808999926, 144.956243599998 -37.821366399953, 144.956917099953 -37.8210132000064, 144.957858399986 -37.8199780000159, 144.960522499975 -37.8198747999727, 144.960809400014 -37.8197300000066, 144.961290500009 -37.8196731999909, 144.961655599964 -37.819358000036, 144.962709400051 -37.8190125000142, 144.964093500022 -37.8188720999518, 144.964835699955 -37.8183051000073, 144.966964299956"
-                // and convert it into a Vec<(Decimal, Decimal)> like
-                // [(-37.8201561999773, 144.98079590002)]
-                paths.push(
-                    path.split(' ')
-                        .chunks(2)
-                        .into_iter()
-                        .map(|mut chunk| {
-                            let lat = chunk
-                                .next()
-                                .and_then(|x| x.split(',').next())
-                                .ok_or_else(|| serde::de::Error::missing_field("latitude"))?
-                                .parse::<Decimal>()
-                                .map_err(|e| {
-                                    serde::de::Error::custom(format!(
-                                        "could not parse Decimal: {}",
-                                        e
-                                    ))
-                                })?;
-                            let lon = chunk
-                                .next()
-                                .and_then(|x| x.split(',').next())
-                                .ok_or_else(|| serde::de::Error::missing_field("longitude"))?
-                                .parse::<Decimal>()
-                                .map_err(|e| {
-                                    serde::de::Error::custom(format!(
-                                        "could not parse Decimal: {}",
-                                        e
-                                    ))
-                                })?;
-                            Ok((lat, lon))
-                        })
-                        .collect::<Result<Vec<_>, A::Error>>()?,
-                )
+            while let Some(Path(path)) = seq.next_element::<Path<C>>()? {
+                paths.push(path);
             }
             Ok(paths)
         }
     }
-    deserializer.deserialize_any(PathVisitor)
+
+    /// A single path, parsed straight from the (possibly borrowed) string without copying it
+    struct Path<C>(Vec<(C, C)>);
+    impl<'de, C: ParseCoordinate> Deserialize<'de> for Path<C> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct Visitor<C>(PhantomData<C>);
+            impl<C: ParseCoordinate> serde::de::Visitor<'_> for Visitor<C> {
+                type Value = Path<C>;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    formatter.write_str("space separated \"latitude, longitude\" pairs")
+                }
+
+                fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                    parse_pairs(v).map(Path).map_err(E::custom)
+                }
+            }
+            deserializer.deserialize_str(Visitor(PhantomData))
+        }
+    }
+
+    deserializer.deserialize_any(PathVisitor(PhantomData))
+}
+
+/// Parse a path like "-37.8683203000288, 145.079655599963 -37.8655618999753, 145.080159200029"
+/// into (latitude, longitude) pairs
+pub fn parse_path(path: &str) -> Result<Vec<(Coordinate, Coordinate)>, String> {
+    parse_pairs(path)
+}
+
+/// [`parse_path`], parsing the coordinates straight to `f64`
+#[cfg(feature = "f64-geopath")]
+pub fn parse_path_f64(path: &str) -> Result<Vec<(Degrees, Degrees)>, String> {
+    parse_pairs(path)
+}
+
+fn parse_pairs<C: ParseCoordinate>(path: &str) -> Result<Vec<(C, C)>, String> {
+    let mut values = path
+        .split(' ')
+        .map(|v| v.trim_end_matches(','))
+        .filter(|v| !v.is_empty());
+    // Each pair takes up ~36 bytes
+    let mut points = Vec::with_capacity(path.len() / 32);
+    while let Some(latitude) = values.next() {
+        let longitude = values
+            .next()
+            .ok_or_else(|| format!("missing longitude after {latitude}"))?;
+        points.push((C::parse(latitude)?, C::parse(longitude)?));
+    }
+    Ok(points)
+}
+
+/// Split a plain decimal like "-37.8683203000288" into its mantissa and scale (-378683203000288, 13).
+/// None for anything unusual (exponents, too many digits for an i64 etc.), to be left to the full parser
+fn scan_decimal(value: &str) -> Option<(i64, u32)> {
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+
+    let mut mantissa = 0i64;
+    let mut scale = None;
+    for (i, byte) in digits.bytes().enumerate() {
+        match byte {
+            b'0'..=b'9' => {
+                mantissa = mantissa
+                    .checked_mul(10)?
+                    .checked_add(i64::from(byte - b'0'))?
+            }
+            b'.' if scale.is_none() => scale = Some((digits.len() - i - 1) as u32),
+            _ => return None,
+        }
+    }
+
+    if digits.len() == usize::from(scale.is_some()) {
+        // No digits at all, "" or "."
+        return None;
+    }
+    Some((
+        if negative { -mantissa } else { mantissa },
+        scale.unwrap_or_default(),
+    ))
+}
+
+/// A coordinate type [`parse_path`] can produce
+pub(crate) trait ParseCoordinate: Sized {
+    fn parse(value: &str) -> Result<Self, String>;
+}
+
+impl ParseCoordinate for Decimal {
+    fn parse(value: &str) -> Result<Self, String> {
+        // Decimal holds at most 28 decimal places, anything past that is left to FromStr
+        match scan_decimal(value)
+            .and_then(|(mantissa, scale)| Decimal::try_new(mantissa, scale).ok())
+        {
+            Some(decimal) => Ok(decimal),
+            None => value
+                .parse()
+                .map_err(|e| format!("could not parse Decimal '{value}': {e}")),
+        }
+    }
+}
+
+#[cfg(feature = "f64-geopath")]
+impl ParseCoordinate for Degrees {
+    fn parse(value: &str) -> Result<Self, String> {
+        // Both the mantissa and the power of ten are exact as f64 here,
+        // so the division is correctly rounded, the same result as str::parse
+        const MAX_EXACT: i64 = 1 << 53;
+        match scan_decimal(value) {
+            Some((mantissa, scale)) if mantissa.abs() <= MAX_EXACT && scale <= 22 => {
+                Ok(Degrees(mantissa as f64 / 10f64.powi(scale as i32)))
+            }
+            _ => value
+                .parse()
+                .map_err(|e| format!("could not parse f64 '{value}': {e}")),
+        }
+    }
+}
+
+pub(crate) fn parse_coordinate(value: &str) -> Result<Coordinate, String> {
+    Coordinate::parse(value)
+}
+
+/// A coordinate as an `f64`
+pub(crate) fn coordinate_to_f64(value: Coordinate) -> f64 {
+    rust_decimal::prelude::ToPrimitive::to_f64(&value).unwrap_or_default()
+}
+
+/// A coordinate as an integer number of `10^-precision` degrees, rounding half away from zero
+pub(crate) fn coordinate_to_scaled(value: Coordinate, precision: u32) -> Option<i64> {
+    use rust_decimal::{RoundingStrategy, prelude::ToPrimitive};
+
+    value
+        .checked_mul(Decimal::from(10i64.pow(precision)))?
+        .round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+        .to_i64()
+}
+
+/// A coordinate back from [`coordinate_to_scaled`]
+pub(crate) fn coordinate_from_scaled(value: i64, precision: u32) -> Coordinate {
+    Decimal::new(value, precision)
 }
 
 pub fn ser_disruption_query<S>(
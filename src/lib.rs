@@ -3,8 +3,53 @@ pub mod core;
 #[cfg(not(target_arch = "wasm32"))]
 pub use core::*;
 
+pub mod departure;
+pub use departure::*;
+
+pub mod diagram;
+pub use diagram::*;
+
+pub mod disruption;
+pub use disruption::*;
+
+pub mod encoding;
+pub use encoding::*;
+
+pub mod export;
+pub use export::*;
+
+pub mod geometry;
+pub use geometry::*;
+
+pub mod gtfs;
+pub use gtfs::*;
+
 pub mod helpers;
 pub use helpers::*;
 
+pub mod projection;
+pub use projection::*;
+
+pub mod realtime;
+pub use realtime::*;
+
+pub mod scope;
+pub use scope::*;
+
+pub mod snap;
+pub use snap::*;
+
+pub mod spatial;
+pub use spatial::*;
+
+pub mod time;
+pub use time::*;
+
 pub mod ty;
 pub use ty::*;
+
+pub mod vehicle;
+pub use vehicle::*;
+
+pub mod view;
+pub use view::*;
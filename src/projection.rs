@@ -0,0 +1,149 @@
+//! Converting projected grid coordinates to latitude and longitude.
+//!
+//! Metro train positions from CIS often come with only an `easting` and `northing`.
+//! The API doesn't document the grid. Eastings in the hundreds of thousands and northings
+//! around 5,800,000 across Melbourne fit MGA zone 55 (GDA94 / GDA2020, EPSG:28355 / 7855).
+//! VicGrid94 (EPSG:3111), the other grid in common use in Victoria, is supported as well,
+//! and [`Grid::detect`] tells the two apart by their ranges, VicGrid's false origin being 2,500,000 for both.
+//!
+//! GDA and WGS84 agree to within a couple of metres, so no datum shift is applied.
+
+use geo_types::Point;
+
+/// Semi-major axis of the GRS80 ellipsoid, in metres
+const SEMI_MAJOR_AXIS: f64 = 6_378_137.0;
+/// Flattening of the GRS80 ellipsoid
+const FLATTENING: f64 = 1.0 / 298.257_222_101;
+
+/// Projected coordinate systems used for Victorian transport data
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Grid {
+    /// Map Grid of Australia, zone 55 (central meridian 147°E)
+    Mga55,
+    /// VicGrid94, a Lambert conformal conic covering the state
+    VicGrid94,
+}
+
+impl Grid {
+    /// Work out which grid an easting and northing in Victoria belong to, None if they fit neither
+    pub fn detect(easting: f64, northing: f64) -> Option<Grid> {
+        if (100_000.0..900_000.0).contains(&easting)
+            && (5_500_000.0..6_500_000.0).contains(&northing)
+        {
+            Some(Grid::Mga55)
+        } else if (2_000_000.0..3_000_000.0).contains(&easting)
+            && (2_000_000.0..3_000_000.0).contains(&northing)
+        {
+            Some(Grid::VicGrid94)
+        } else {
+            None
+        }
+    }
+
+    /// Convert an easting and northing on this grid to longitude (x) and latitude (y)
+    pub fn to_wgs84(&self, easting: f64, northing: f64) -> Point<f64> {
+        match self {
+            Grid::Mga55 => inverse_transverse_mercator(easting, northing),
+            Grid::VicGrid94 => inverse_lambert(easting, northing),
+        }
+    }
+}
+
+fn eccentricity_squared() -> f64 {
+    FLATTENING * (2.0 - FLATTENING)
+}
+
+/// UTM inverse (Snyder, Map Projections: A Working Manual, §8), southern hemisphere zone 55
+fn inverse_transverse_mercator(easting: f64, northing: f64) -> Point<f64> {
+    const SCALE: f64 = 0.9996;
+    const FALSE_EASTING: f64 = 500_000.0;
+    const FALSE_NORTHING: f64 = 10_000_000.0;
+    const CENTRAL_MERIDIAN: f64 = 147.0;
+
+    let a = SEMI_MAJOR_AXIS;
+    let e2 = eccentricity_squared();
+    let ep2 = e2 / (1.0 - e2);
+
+    let m = (northing - FALSE_NORTHING) / SCALE;
+    let mu = m / (a * (1.0 - e2 / 4.0 - 3.0 * e2.powi(2) / 64.0 - 5.0 * e2.powi(3) / 256.0));
+
+    let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+    let phi1 = mu
+        + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+        + (21.0 * e1.powi(2) / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+        + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+        + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+
+    let (sin, cos, tan) = (phi1.sin(), phi1.cos(), phi1.tan());
+    let c1 = ep2 * cos.powi(2);
+    let t1 = tan.powi(2);
+    let n1 = a / (1.0 - e2 * sin.powi(2)).sqrt();
+    let r1 = a * (1.0 - e2) / (1.0 - e2 * sin.powi(2)).powf(1.5);
+    let d = (easting - FALSE_EASTING) / (n1 * SCALE);
+
+    let latitude = phi1
+        - (n1 * tan / r1)
+            * (d.powi(2) / 2.0
+                - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1.powi(2) - 9.0 * ep2) * d.powi(4) / 24.0
+                + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1.powi(2)
+                    - 252.0 * ep2
+                    - 3.0 * c1.powi(2))
+                    * d.powi(6)
+                    / 720.0);
+    let longitude = (d - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
+        + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1.powi(2) + 8.0 * ep2 + 24.0 * t1.powi(2))
+            * d.powi(5)
+            / 120.0)
+        / cos;
+
+    Point::new(
+        CENTRAL_MERIDIAN + longitude.to_degrees(),
+        latitude.to_degrees(),
+    )
+}
+
+/// Lambert conformal conic inverse (Snyder §15), with VicGrid94's parameters
+fn inverse_lambert(easting: f64, northing: f64) -> Point<f64> {
+    const STANDARD_PARALLELS: (f64, f64) = (-36.0, -38.0);
+    const ORIGIN: (f64, f64) = (145.0, -37.0);
+    const FALSE_ORIGIN: f64 = 2_500_000.0;
+
+    let a = SEMI_MAJOR_AXIS;
+    let e = eccentricity_squared().sqrt();
+
+    let m = |phi: f64| phi.cos() / (1.0 - (e * phi.sin()).powi(2)).sqrt();
+    let t = |phi: f64| {
+        (std::f64::consts::FRAC_PI_4 - phi / 2.0).tan()
+            / ((1.0 - e * phi.sin()) / (1.0 + e * phi.sin())).powf(e / 2.0)
+    };
+
+    let (phi1, phi2) = (
+        STANDARD_PARALLELS.0.to_radians(),
+        STANDARD_PARALLELS.1.to_radians(),
+    );
+    let n = (m(phi1).ln() - m(phi2).ln()) / (t(phi1).ln() - t(phi2).ln());
+    let f = m(phi1) / (n * t(phi1).powf(n));
+    let rho0 = a * f * t(ORIGIN.1.to_radians()).powf(n);
+
+    let x = easting - FALSE_ORIGIN;
+    let y = rho0 - (northing - FALSE_ORIGIN);
+    let sign = n.signum();
+    let rho = sign * x.hypot(y);
+    let theta = (sign * x).atan2(sign * y);
+    let t_value = (rho / (a * f)).powf(1.0 / n);
+
+    // Latitude has no closed form, iterate until it settles
+    let mut phi = std::f64::consts::FRAC_PI_2 - 2.0 * t_value.atan();
+    for _ in 0..10 {
+        let next = std::f64::consts::FRAC_PI_2
+            - 2.0
+                * (t_value * ((1.0 - e * phi.sin()) / (1.0 + e * phi.sin())).powf(e / 2.0)).atan();
+        let settled = (next - phi).abs() < 1e-12;
+        phi = next;
+        if settled {
+            break;
+        }
+    }
+
+    Point::new(ORIGIN.0 + (theta / n).to_degrees(), phi.to_degrees())
+}
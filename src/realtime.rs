@@ -0,0 +1,653 @@
+//! Producing GTFS-Realtime feeds from API responses, for trip planners that only speak GTFS.
+//!
+//! Add responses to a [`RealtimeFeed`], then encode it as a protobuf `FeedMessage`.
+//! Routes are identified by [`Route::gtfs_id`](crate::ty::Route::gtfs_id), and stops by their stop ID, which PTV's GTFS `stop_id`s mostly are.
+//! The API's run_ref is used as the trip_id, which won't match a static feed's,
+//! so trips are also described by route and start time where they're known.
+//! Build the feed with [`RealtimeFeed::with_gtfs`] to look the real trip_id and stop_ids up in a static feed instead.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, Timelike, Utc};
+use prost::Message;
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::{
+    gtfs::{Gtfs, format_time, service_day_start},
+    time::MelbourneTime,
+    ty::{
+        Departure, DeparturesResponse, Disruption, DisruptionId, DisruptionType, PatternResponse,
+        RouteId, RouteWithGeoPath, Run, RunRef, RunStatus, StopId,
+    },
+};
+
+/// The parts of `gtfs-realtime.proto` (version 2.0) the feed uses, field numbers as in the spec
+pub mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FeedMessage {
+        #[prost(message, required, tag = "1")]
+        pub header: FeedHeader,
+        #[prost(message, repeated, tag = "2")]
+        pub entity: Vec<FeedEntity>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FeedHeader {
+        #[prost(string, required, tag = "1")]
+        pub gtfs_realtime_version: String,
+        #[prost(enumeration = "Incrementality", optional, tag = "2")]
+        pub incrementality: Option<i32>,
+        /// POSIX time the feed was created
+        #[prost(uint64, optional, tag = "3")]
+        pub timestamp: Option<u64>,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Incrementality {
+        FullDataset = 0,
+        Differential = 1,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FeedEntity {
+        #[prost(string, required, tag = "1")]
+        pub id: String,
+        #[prost(bool, optional, tag = "2")]
+        pub is_deleted: Option<bool>,
+        #[prost(message, optional, tag = "3")]
+        pub trip_update: Option<TripUpdate>,
+        #[prost(message, optional, tag = "4")]
+        pub vehicle: Option<VehiclePosition>,
+        #[prost(message, optional, tag = "5")]
+        pub alert: Option<Alert>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct TripUpdate {
+        #[prost(message, required, tag = "1")]
+        pub trip: TripDescriptor,
+        #[prost(message, repeated, tag = "2")]
+        pub stop_time_update: Vec<StopTimeUpdate>,
+        #[prost(message, optional, tag = "3")]
+        pub vehicle: Option<VehicleDescriptor>,
+        #[prost(uint64, optional, tag = "4")]
+        pub timestamp: Option<u64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct StopTimeEvent {
+        /// Seconds late (negative if early)
+        #[prost(int32, optional, tag = "1")]
+        pub delay: Option<i32>,
+        /// POSIX time
+        #[prost(int64, optional, tag = "2")]
+        pub time: Option<i64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct StopTimeUpdate {
+        #[prost(uint32, optional, tag = "1")]
+        pub stop_sequence: Option<u32>,
+        #[prost(message, optional, tag = "2")]
+        pub arrival: Option<StopTimeEvent>,
+        #[prost(message, optional, tag = "3")]
+        pub departure: Option<StopTimeEvent>,
+        #[prost(string, optional, tag = "4")]
+        pub stop_id: Option<String>,
+        #[prost(enumeration = "StopScheduleRelationship", optional, tag = "5")]
+        pub schedule_relationship: Option<i32>,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum StopScheduleRelationship {
+        Scheduled = 0,
+        Skipped = 1,
+        NoData = 2,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct TripDescriptor {
+        #[prost(string, optional, tag = "1")]
+        pub trip_id: Option<String>,
+        /// Local time the trip starts, "HH:MM:SS"
+        #[prost(string, optional, tag = "2")]
+        pub start_time: Option<String>,
+        /// Service day the trip runs on, "YYYYMMDD"
+        #[prost(string, optional, tag = "3")]
+        pub start_date: Option<String>,
+        #[prost(enumeration = "TripScheduleRelationship", optional, tag = "4")]
+        pub schedule_relationship: Option<i32>,
+        #[prost(string, optional, tag = "5")]
+        pub route_id: Option<String>,
+        #[prost(uint32, optional, tag = "6")]
+        pub direction_id: Option<u32>,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum TripScheduleRelationship {
+        Scheduled = 0,
+        Added = 1,
+        Unscheduled = 2,
+        Canceled = 3,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct VehicleDescriptor {
+        #[prost(string, optional, tag = "1")]
+        pub id: Option<String>,
+        #[prost(string, optional, tag = "2")]
+        pub label: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct VehiclePosition {
+        #[prost(message, optional, tag = "1")]
+        pub trip: Option<TripDescriptor>,
+        #[prost(message, optional, tag = "2")]
+        pub position: Option<Position>,
+        #[prost(enumeration = "VehicleStopStatus", optional, tag = "4")]
+        pub current_status: Option<i32>,
+        #[prost(uint64, optional, tag = "5")]
+        pub timestamp: Option<u64>,
+        #[prost(string, optional, tag = "7")]
+        pub stop_id: Option<String>,
+        #[prost(message, optional, tag = "8")]
+        pub vehicle: Option<VehicleDescriptor>,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum VehicleStopStatus {
+        IncomingAt = 0,
+        StoppedAt = 1,
+        InTransitTo = 2,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Position {
+        #[prost(float, required, tag = "1")]
+        pub latitude: f32,
+        #[prost(float, required, tag = "2")]
+        pub longitude: f32,
+        /// Degrees clockwise from north
+        #[prost(float, optional, tag = "3")]
+        pub bearing: Option<f32>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Alert {
+        #[prost(message, repeated, tag = "1")]
+        pub active_period: Vec<TimeRange>,
+        #[prost(message, repeated, tag = "5")]
+        pub informed_entity: Vec<EntitySelector>,
+        #[prost(enumeration = "Cause", optional, tag = "6")]
+        pub cause: Option<i32>,
+        #[prost(enumeration = "Effect", optional, tag = "7")]
+        pub effect: Option<i32>,
+        #[prost(message, optional, tag = "8")]
+        pub url: Option<TranslatedString>,
+        #[prost(message, optional, tag = "10")]
+        pub header_text: Option<TranslatedString>,
+        #[prost(message, optional, tag = "11")]
+        pub description_text: Option<TranslatedString>,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Cause {
+        UnknownCause = 1,
+        OtherCause = 2,
+        TechnicalProblem = 3,
+        Strike = 4,
+        Demonstration = 5,
+        Accident = 6,
+        Holiday = 7,
+        Weather = 8,
+        Maintenance = 9,
+        Construction = 10,
+        PoliceActivity = 11,
+        MedicalEmergency = 12,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Effect {
+        NoService = 1,
+        ReducedService = 2,
+        SignificantDelays = 3,
+        Detour = 4,
+        AdditionalService = 5,
+        ModifiedService = 6,
+        OtherEffect = 7,
+        UnknownEffect = 8,
+        StopMoved = 9,
+        NoEffect = 10,
+        AccessibilityIssue = 11,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct TimeRange {
+        #[prost(uint64, optional, tag = "1")]
+        pub start: Option<u64>,
+        #[prost(uint64, optional, tag = "2")]
+        pub end: Option<u64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct EntitySelector {
+        #[prost(string, optional, tag = "2")]
+        pub route_id: Option<String>,
+        #[prost(string, optional, tag = "5")]
+        pub stop_id: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct TranslatedString {
+        #[prost(message, repeated, tag = "1")]
+        pub translation: Vec<Translation>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Translation {
+        #[prost(string, required, tag = "1")]
+        pub text: String,
+        #[prost(string, optional, tag = "2")]
+        pub language: Option<String>,
+    }
+
+    impl TranslatedString {
+        /// English text
+        pub fn english(text: impl Into<String>) -> Self {
+            TranslatedString {
+                translation: vec![Translation {
+                    text: text.into(),
+                    language: Some("en".into()),
+                }],
+            }
+        }
+    }
+}
+
+fn posix(time: DateTime<Utc>) -> u64 {
+    time.timestamp().max(0) as u64
+}
+
+/// Trips starting before this hour of the morning are taken to be running on from the service day before,
+/// when there's no static feed to say
+const SERVICE_DAY_END_HOUR: u32 = 3;
+
+/// The service day a trip starting at `start` most likely runs on
+fn service_date(start: DateTime<Utc>) -> NaiveDate {
+    let start = start.to_melbourne();
+    let date = start.date_naive();
+    match start.hour() < SERVICE_DAY_END_HOUR {
+        true => date.pred_opt().unwrap_or(date),
+        false => date,
+    }
+}
+
+/// A GTFS-Realtime feed of trip updates, vehicle positions and alerts, built up from API responses.
+/// Adding a run or disruption again replaces what was there, except a trip's start time and stop time updates,
+/// which are merged so departures for a run from different stops end up in one trip update
+#[derive(Debug, Clone, Default)]
+pub struct RealtimeFeed<'a> {
+    gtfs: Option<&'a Gtfs>,
+    trip_updates: BTreeMap<RunRef, proto::TripUpdate>,
+    vehicles: BTreeMap<RunRef, proto::VehiclePosition>,
+    alerts: BTreeMap<DisruptionId, proto::Alert>,
+}
+
+impl<'a> RealtimeFeed<'a> {
+    pub fn new() -> Self {
+        RealtimeFeed::default()
+    }
+
+    /// A feed with trip_ids and stop_ids looked up in a static feed,
+    /// using [`Gtfs::find_trip`] and [`Gtfs::gtfs_stop_id`]
+    pub fn with_gtfs(gtfs: &'a Gtfs) -> Self {
+        RealtimeFeed {
+            gtfs: Some(gtfs),
+            ..Default::default()
+        }
+    }
+
+    fn stop_id(&self, stop_id: StopId) -> String {
+        self.gtfs
+            .and_then(|gtfs| gtfs.gtfs_stop_id(stop_id))
+            .map_or_else(|| stop_id.to_string(), str::to_owned)
+    }
+
+    /// Describe a run by its departures, which are in order, the first being where the run starts if `from_origin`.
+    /// Also whether the trip was found in the static feed, in which case its trip_id is the feed's
+    fn trip(
+        &self,
+        run_ref: &RunRef,
+        run: Option<&Run>,
+        departures: &[&Departure],
+        routes: &BTreeMap<RouteId, RouteWithGeoPath>,
+        from_origin: bool,
+    ) -> (proto::TripDescriptor, bool) {
+        let route = departures
+            .first()
+            .map(|d| d.route_id)
+            .or(run.map(|r| r.route_id))
+            .and_then(|id| routes.get(&id))
+            .map(|r| &r.route);
+
+        let found = self.gtfs.zip(route).and_then(|(gtfs, route)| {
+            departures
+                .iter()
+                .find_map(|d| gtfs.find_trip_date(route, d))
+        });
+        let trip_id = found.map_or(run_ref, |(trip_id, _)| trip_id);
+
+        // GTFS times count from the start of the service day, so trips after midnight start at 24:00:00 or later
+        let start = departures
+            .first()
+            .filter(|_| from_origin)
+            .and_then(|d| d.scheduled_departure)
+            .and_then(|start| {
+                let date = found.map_or_else(|| service_date(start), |(_, date)| date);
+                let time = (start - service_day_start(date)?).num_seconds();
+                Some((format_time(time), date))
+            });
+        let (start_time, start_date) = start.unzip();
+        // The static feed knows the service day even when we don't know where the run started
+        let start_date = start_date
+            .or(found.map(|(_, date)| date))
+            .map(|date| date.format("%Y%m%d").to_string());
+
+        let schedule_relationship = match run.map(|r| &r.status) {
+            Some(RunStatus::Cancelled) => proto::TripScheduleRelationship::Canceled,
+            Some(RunStatus::Added) => proto::TripScheduleRelationship::Added,
+            _ => proto::TripScheduleRelationship::Scheduled,
+        };
+
+        let trip = proto::TripDescriptor {
+            trip_id: Some(trip_id.to_string()),
+            start_time,
+            start_date,
+            schedule_relationship: Some(schedule_relationship.into()),
+            route_id: route.map(|r| r.gtfs_id.clone()).filter(|id| !id.is_empty()),
+            direction_id: None,
+        };
+        (trip, found.is_some())
+    }
+
+    fn vehicle(run: &Run) -> Option<proto::VehicleDescriptor> {
+        let descriptor = run.vehicle_descriptor.as_ref()?;
+        Some(proto::VehicleDescriptor {
+            id: descriptor.id.clone(),
+            label: descriptor.description.clone(),
+        })
+    }
+
+    fn add_runs(
+        &mut self,
+        departures: &[Departure],
+        routes: &BTreeMap<RouteId, RouteWithGeoPath>,
+        runs: &BTreeMap<RunRef, Run>,
+        pattern: Option<&PatternResponse>,
+    ) {
+        let mut by_run = BTreeMap::<&RunRef, Vec<&Departure>>::new();
+        for departure in departures {
+            by_run
+                .entry(&departure.run_ref)
+                .or_default()
+                .push(departure);
+        }
+
+        for (run_ref, mut departures) in by_run {
+            departures.sort_by_key(|d| (d.departure_sequence, d.scheduled_departure));
+            let run = runs.get(run_ref);
+            let (trip, from_gtfs) = self.trip(run_ref, run, &departures, routes, pattern.is_some());
+            let cancelled = trip.schedule_relationship
+                == Some(proto::TripScheduleRelationship::Canceled.into());
+
+            let updates = departures
+                .iter()
+                .filter(|_| !cancelled)
+                .filter_map(|departure| {
+                    let estimated = departure.estimated_departure?;
+                    Some(proto::StopTimeUpdate {
+                        // A static feed's stop_sequence isn't the API's departure_sequence, so leave it to the stop_id
+                        stop_sequence: u32::try_from(departure.departure_sequence)
+                            .ok()
+                            .filter(|s| *s > 0 && !from_gtfs),
+                        arrival: None,
+                        departure: Some(proto::StopTimeEvent {
+                            delay: departure
+                                .delay()
+                                .and_then(|d| i32::try_from(d.num_seconds()).ok()),
+                            time: Some(estimated.timestamp()),
+                        }),
+                        stop_id: Some(self.stop_id(departure.stop_id)),
+                        schedule_relationship: Some(
+                            proto::StopScheduleRelationship::Scheduled.into(),
+                        ),
+                    })
+                })
+                .collect::<Vec<_>>();
+            if updates.is_empty() && !cancelled {
+                continue;
+            }
+
+            let mut update = proto::TripUpdate {
+                trip,
+                stop_time_update: Vec::new(),
+                vehicle: run.and_then(RealtimeFeed::vehicle),
+                timestamp: run
+                    .and_then(|r| r.vehicle_position.as_ref())
+                    .map(|p| posix(p.datetime)),
+            };
+            if let Some(previous) = self.trip_updates.remove(run_ref) {
+                // A departures response doesn't know when the run started, a pattern might have
+                let trip = &mut update.trip;
+                trip.start_time = trip.start_time.take().or(previous.trip.start_time);
+                trip.start_date = trip.start_date.take().or(previous.trip.start_date);
+                // A cancelled trip has no stop times, whatever was known about it before
+                if !cancelled {
+                    update.stop_time_update = previous
+                        .stop_time_update
+                        .into_iter()
+                        .filter(|old| !updates.iter().any(|new| new.stop_id == old.stop_id))
+                        .collect();
+                }
+            }
+            if !cancelled {
+                update.stop_time_update.extend(updates);
+                update
+                    .stop_time_update
+                    .sort_by_key(|u| u.departure.as_ref().and_then(|d| d.time));
+            }
+            self.trip_updates.insert(run_ref.clone(), update);
+        }
+
+        for (run_ref, run) in runs {
+            let Some(vehicle) = &run.vehicle_position else {
+                continue;
+            };
+            let Some(point) = vehicle.coordinates() else {
+                continue;
+            };
+
+            let run_departures = departures
+                .iter()
+                .filter(|d| &d.run_ref == run_ref)
+                .collect::<Vec<_>>();
+            let next_stop = pattern
+                .and_then(|pattern| run.snap_to_pattern(pattern))
+                .and_then(|position| position.next_stop);
+
+            self.vehicles.insert(
+                run_ref.clone(),
+                proto::VehiclePosition {
+                    trip: Some(
+                        self.trip(
+                            run_ref,
+                            Some(run),
+                            &run_departures,
+                            routes,
+                            pattern.is_some(),
+                        )
+                        .0,
+                    ),
+                    position: Some(proto::Position {
+                        latitude: point.y() as f32,
+                        longitude: point.x() as f32,
+                        bearing: vehicle.bearing.and_then(|b| b.to_f32()),
+                    }),
+                    current_status: next_stop.map(|_| proto::VehicleStopStatus::InTransitTo.into()),
+                    timestamp: Some(posix(vehicle.datetime)),
+                    stop_id: next_stop.map(|s| self.stop_id(s.stop.id)),
+                    vehicle: RealtimeFeed::vehicle(run),
+                },
+            );
+        }
+    }
+
+    /// Trip updates from the departures' estimated times, vehicle positions of the runs,
+    /// and alerts for any disruptions expanded in the response.
+    /// Runs are only seen from the stops in the response, so their start time is left out
+    pub fn add_departures(&mut self, response: &DeparturesResponse) -> &mut Self {
+        self.add_runs(&response.departures, &response.routes, &response.runs, None);
+        self.add_disruptions(response.disruptions.values())
+    }
+
+    /// Trip updates, vehicle positions and alerts from a stopping pattern.
+    /// Vehicles are placed in transit to their next stop if they can be snapped to the route
+    /// (see [`Run::snap_to_pattern`])
+    pub fn add_pattern(&mut self, response: &PatternResponse) -> &mut Self {
+        self.add_runs(
+            &response.departures,
+            &response.routes,
+            &response.runs,
+            Some(response),
+        );
+        self.add_disruptions(&response.disruptions)
+    }
+
+    /// An alert per disruption, informing each of its routes and stops.
+    /// Disruptions with neither (network wide notices) are left out, there's nothing to inform
+    pub fn add_disruptions<'b>(
+        &mut self,
+        disruptions: impl IntoIterator<Item = &'b Disruption>,
+    ) -> &mut Self {
+        for disruption in disruptions {
+            // The disruption doesn't say which of its stops are on which of its routes,
+            // so each is informed on its own
+            let routes = disruption.routes.iter().map(|r| proto::EntitySelector {
+                route_id: Some(r.route.gtfs_id.clone()),
+                stop_id: None,
+            });
+            let stops = disruption.stops.iter().map(|s| proto::EntitySelector {
+                route_id: None,
+                stop_id: Some(self.stop_id(s.id)),
+            });
+            let informed_entity = routes
+                .chain(stops)
+                .filter(|e| {
+                    e.route_id
+                        .as_deref()
+                        .or(e.stop_id.as_deref())
+                        .is_some_and(|id| !id.is_empty())
+                })
+                .collect::<Vec<_>>();
+            if informed_entity.is_empty() {
+                continue;
+            }
+
+            let (cause, effect) = match disruption.disruption_type {
+                DisruptionType::PlannedWorks => {
+                    (proto::Cause::Maintenance, proto::Effect::ModifiedService)
+                }
+                DisruptionType::PlannedClosure => {
+                    (proto::Cause::Maintenance, proto::Effect::NoService)
+                }
+                DisruptionType::MajorDelays => {
+                    (proto::Cause::UnknownCause, proto::Effect::SignificantDelays)
+                }
+                DisruptionType::PartSuspended => {
+                    (proto::Cause::UnknownCause, proto::Effect::ReducedService)
+                }
+                DisruptionType::Suspended => (proto::Cause::UnknownCause, proto::Effect::NoService),
+                DisruptionType::MinorDelays | DisruptionType::ServiceInformation => {
+                    (proto::Cause::UnknownCause, proto::Effect::OtherEffect)
+                }
+                DisruptionType::Other(_) => {
+                    (proto::Cause::UnknownCause, proto::Effect::UnknownEffect)
+                }
+            };
+
+            self.alerts.insert(
+                disruption.disruption_id,
+                proto::Alert {
+                    active_period: vec![proto::TimeRange {
+                        start: Some(posix(disruption.from_date)),
+                        end: disruption.to_date.map(posix),
+                    }],
+                    informed_entity,
+                    cause: Some(cause.into()),
+                    effect: Some(effect.into()),
+                    url: Some(disruption.url.as_str())
+                        .filter(|url| !url.is_empty())
+                        .map(proto::TranslatedString::english),
+                    header_text: Some(proto::TranslatedString::english(
+                        disruption.summary(usize::MAX),
+                    )),
+                    description_text: Some(proto::TranslatedString::english(
+                        disruption.plain_text(),
+                    )),
+                },
+            );
+        }
+        self
+    }
+
+    /// The whole feed, created at `timestamp`
+    pub fn to_message(&self, timestamp: DateTime<Utc>) -> proto::FeedMessage {
+        let entity = |id: String| proto::FeedEntity {
+            id,
+            is_deleted: None,
+            trip_update: None,
+            vehicle: None,
+            alert: None,
+        };
+
+        let trip_updates = self
+            .trip_updates
+            .iter()
+            .map(|(run_ref, update)| proto::FeedEntity {
+                trip_update: Some(update.clone()),
+                ..entity(format!("trip-{run_ref}"))
+            });
+        let vehicles = self
+            .vehicles
+            .iter()
+            .map(|(run_ref, vehicle)| proto::FeedEntity {
+                vehicle: Some(vehicle.clone()),
+                ..entity(format!("vehicle-{run_ref}"))
+            });
+        let alerts = self.alerts.iter().map(|(id, alert)| proto::FeedEntity {
+            alert: Some(alert.clone()),
+            ..entity(format!("alert-{id}"))
+        });
+
+        proto::FeedMessage {
+            header: proto::FeedHeader {
+                gtfs_realtime_version: "2.0".into(),
+                incrementality: Some(proto::Incrementality::FullDataset.into()),
+                timestamp: Some(posix(timestamp)),
+            },
+            entity: trip_updates.chain(vehicles).chain(alerts).collect(),
+        }
+    }
+
+    /// The whole feed, created at `timestamp`, encoded as a protobuf `FeedMessage`
+    pub fn encode(&self, timestamp: DateTime<Utc>) -> Vec<u8> {
+        self.to_message(timestamp).encode_to_vec()
+    }
+}
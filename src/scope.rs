@@ -0,0 +1,436 @@
+//! Best-effort extraction of a disruption's scope from its text.
+//!
+//! The structured fields on a [`Disruption`] only tell part of the story, the rest lives in sentences like
+//! "Buses replace trains between Caulfield and Westall from 8:30pm Saturday to last service Sunday".
+//! This pulls the segment endpoints, time windows and replacement services out of those sentences.
+//! It's a heuristic, so treat anything it finds as a hint rather than gospel.
+
+use chrono::{Month, NaiveTime, Weekday};
+use itertools::Itertools;
+
+use crate::ty::{Disruption, StopId};
+
+/// What a disruption affects, as described in its text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DisruptionScope {
+    /// Sections of line between two stops
+    pub segments: Vec<ScopeSegment>,
+    /// When the disruption applies
+    pub windows: Vec<TimeWindow>,
+    /// Buses replace the usual service
+    pub replacement_buses: bool,
+    /// Coaches replace the usual service
+    pub replacement_coaches: bool,
+}
+
+/// A section of line, e.g. "between Caulfield and Westall"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeSegment {
+    pub from: ScopeStop,
+    pub to: ScopeStop,
+}
+
+/// A stop named in the text, matched against known stops where possible
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeStop {
+    /// The name as written
+    pub name: String,
+    pub stop_id: Option<StopId>,
+}
+
+/// A period of time, e.g. "from 8:30pm Saturday to last service Sunday".
+/// Either end can be missing ("until 10pm", "after 8pm")
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub start: Option<ScopeTime>,
+    pub end: Option<ScopeTime>,
+}
+
+/// A point in time as written in the text. Every part is optional, since
+/// the text rarely spells out all of them (and never the year)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScopeTime {
+    pub time: Option<TimeOfDay>,
+    pub weekday: Option<Weekday>,
+    pub day: Option<u32>,
+    pub month: Option<Month>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeOfDay {
+    At(NaiveTime),
+    FirstService,
+    LastService,
+}
+
+impl ScopeTime {
+    fn is_empty(&self) -> bool {
+        self == &ScopeTime::default()
+    }
+}
+
+/// Words that end a stop name or time expression
+const TERMINATORS: &[&str] = &[
+    ",",
+    ";",
+    "(",
+    " and ",
+    " from ",
+    " to ",
+    " until ",
+    " due ",
+    " while ",
+    " for ",
+    " after ",
+    " before ",
+    " between ",
+    " daily",
+    " each ",
+    " every ",
+    " as ",
+    " because ",
+    " with ",
+    " where ",
+    " replace",
+    " will ",
+];
+
+/// Words that end a stop name, but not a time expression ("from 8pm on Saturday")
+const STOP_TERMINATORS: &[&str] = &[" on ", " at ", " this ", " tonight", " today", " tomorrow"];
+
+impl DisruptionScope {
+    /// Extract the scope from free text, matching stop names against `stops`
+    pub fn extract<'a>(text: &str, stops: impl IntoIterator<Item = (StopId, &'a str)>) -> Self {
+        let stops = stops
+            .into_iter()
+            .map(|(id, name)| (id, normalise_stop(name)))
+            .collect::<Vec<_>>();
+
+        let lower = text.to_ascii_lowercase();
+        let mut scope = DisruptionScope {
+            replacement_buses: [
+                "buses replace",
+                "buses will replace",
+                "replacement bus",
+                "bus replacement",
+            ]
+            .iter()
+            .any(|x| lower.contains(x)),
+            replacement_coaches: [
+                "coaches replace",
+                "coaches will replace",
+                "replacement coach",
+                "coach replacement",
+            ]
+            .iter()
+            .any(|x| lower.contains(x)),
+            ..Default::default()
+        };
+
+        for sentence in sentences(text) {
+            scope.extract_sentence(sentence, &stops);
+        }
+
+        // The title and description usually say the same thing
+        scope.segments = scope.segments.into_iter().unique_by(segment_key).collect();
+        scope.windows = scope
+            .windows
+            .into_iter()
+            .fold(Vec::new(), |mut windows, window| {
+                if !windows.contains(&window) {
+                    windows.push(window);
+                }
+                windows
+            });
+        scope
+    }
+
+    fn extract_sentence(&mut self, sentence: &str, stops: &[(StopId, String)]) {
+        let lower = sentence.to_ascii_lowercase();
+
+        // "between X and Y", either stops or times
+        for (start, _) in lower.match_indices("between ") {
+            let from_start = start + "between ".len();
+            let Some(and) = lower[from_start..].find(" and ") else {
+                continue;
+            };
+            let from = &sentence[from_start..from_start + and];
+            let to_start = from_start + and + " and ".len();
+            let to = until_terminator(&sentence[to_start..]);
+
+            match (parse_time(from), parse_time(to)) {
+                (Some(start), Some(end)) if start.time.is_some() || end.time.is_some() => {
+                    self.windows.push(TimeWindow {
+                        start: Some(start),
+                        end: Some(end),
+                    })
+                }
+                _ => self.push_segment(from, to, stops),
+            }
+        }
+
+        // "from X to Y" / "from X until Y", either times or stops
+        let mut used_ends = Vec::new();
+        for (start, _) in lower.match_indices("from ") {
+            if start > 0 && !lower[..start].ends_with(' ') {
+                continue;
+            }
+
+            let from_start = start + "from ".len();
+            let from = until_terminator(&sentence[from_start..]);
+            // `from` is trimmed, so find where it ends from its position in the sentence
+            let from_end = from.as_ptr() as usize - sentence.as_ptr() as usize + from.len();
+            let to = [" to ", " until "].iter().find_map(|sep| {
+                lower[from_end..]
+                    .starts_with(sep)
+                    .then(|| from_end + sep.len())
+            });
+
+            match to {
+                Some(to_start) => {
+                    let to = until_terminator(&sentence[to_start..]);
+                    used_ends.push(to_start);
+                    match (parse_time(from), parse_time(to)) {
+                        (None, None) => self.push_segment(from, to, stops),
+                        (start, end) => self.windows.push(TimeWindow { start, end }),
+                    }
+                }
+                None => {
+                    if let Some(start) = parse_time(from) {
+                        self.windows.push(TimeWindow {
+                            start: Some(start),
+                            end: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Open ended, "until 10pm" / "after 8pm"
+        for (word, is_end) in [("until ", true), ("after ", false)] {
+            for (start, _) in lower.match_indices(word) {
+                let expr_start = start + word.len();
+                if used_ends.contains(&expr_start) || (start > 0 && !lower[..start].ends_with(' '))
+                {
+                    continue;
+                }
+
+                if let Some(time) = parse_time(until_terminator(&sentence[expr_start..])) {
+                    let (start, end) = if is_end {
+                        (None, Some(time))
+                    } else {
+                        (Some(time), None)
+                    };
+                    self.windows.push(TimeWindow { start, end });
+                }
+            }
+        }
+    }
+
+    fn push_segment(&mut self, from: &str, to: &str, stops: &[(StopId, String)]) {
+        let (Some(from), Some(to)) = (scope_stop(from, stops), scope_stop(to, stops)) else {
+            return;
+        };
+        self.segments.push(ScopeSegment { from, to });
+    }
+}
+
+impl Disruption {
+    /// Best-effort scope of the disruption, from its title and description.
+    /// Stop names are matched against the disruption's own stops
+    pub fn scope(&self) -> DisruptionScope {
+        self.scope_with_stops([])
+    }
+
+    /// As [`Disruption::scope`], also matching stop names against `stops`
+    /// (e.g. every stop on the affected route)
+    pub fn scope_with_stops<'a>(
+        &'a self,
+        stops: impl IntoIterator<Item = (StopId, &'a str)>,
+    ) -> DisruptionScope {
+        let text = format!("{}\n{}", self.title, self.plain_text());
+        DisruptionScope::extract(
+            &text,
+            self.stops
+                .iter()
+                .map(|stop| (stop.id, stop.name.as_str()))
+                .chain(stops),
+        )
+    }
+}
+
+fn segment_key(segment: &ScopeSegment) -> (String, String) {
+    (
+        normalise_stop(&segment.from.name),
+        normalise_stop(&segment.to.name),
+    )
+}
+
+/// Split on full stops, but not the ones in "8.30pm"
+fn sentences(text: &str) -> impl Iterator<Item = &str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let end_of_sentence = match c {
+            '\n' | '!' | '?' => true,
+            '.' => chars.peek().is_none_or(|(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if end_of_sentence {
+            sentences.push(&text[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    sentences.push(&text[start..]);
+    sentences
+        .into_iter()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+fn until_terminator(s: &str) -> &str {
+    until_any(s, TERMINATORS)
+}
+
+fn until_any<'a>(s: &'a str, terminators: &[&str]) -> &'a str {
+    let lower = s.to_ascii_lowercase();
+    let end = terminators
+        .iter()
+        .filter_map(|t| lower.find(t))
+        .min()
+        .unwrap_or(s.len());
+    s[..end].trim()
+}
+
+fn normalise_stop(name: &str) -> String {
+    let name = name.trim().to_ascii_lowercase();
+    let name = name.strip_prefix("the ").unwrap_or(&name);
+    [" railway station", " station", " stn"]
+        .iter()
+        .fold(name, |name, suffix| {
+            name.strip_suffix(suffix).unwrap_or(name)
+        })
+        .trim()
+        .to_string()
+}
+
+fn scope_stop(name: &str, stops: &[(StopId, String)]) -> Option<ScopeStop> {
+    let name = until_any(name, STOP_TERMINATORS);
+    let words = name.split_whitespace().count();
+    if name.is_empty() || words > 5 || !name.starts_with(|c: char| c.is_uppercase()) {
+        return None;
+    }
+
+    let normalised = normalise_stop(name);
+    Some(ScopeStop {
+        name: name.to_string(),
+        stop_id: stops
+            .iter()
+            .find(|(_, stop)| *stop == normalised)
+            .map(|(id, _)| *id),
+    })
+}
+
+/// Parse a time expression like "8:30pm Saturday 4 May" or "last service Sunday".
+/// Returns None unless every word is part of the expression
+fn parse_time(s: &str) -> Option<ScopeTime> {
+    let words = s
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|w| {
+            w.trim_matches(|c: char| c == '.' || c == '\'')
+                .to_ascii_lowercase()
+        })
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut time = ScopeTime::default();
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i].as_str();
+        let next = words.get(i + 1).map(String::as_str);
+
+        if let Some(t) = parse_clock(word, next) {
+            time.time = Some(TimeOfDay::At(t.0));
+            i += t.1;
+            continue;
+        }
+
+        match (word, next) {
+            ("first" | "last", Some(next)) if is_service_word(next) => {
+                time.time = Some(match word {
+                    "first" => TimeOfDay::FirstService,
+                    _ => TimeOfDay::LastService,
+                });
+                i += 2;
+                continue;
+            }
+            ("midnight", _) => time.time = Some(TimeOfDay::At(NaiveTime::MIN)),
+            ("midday" | "noon", _) => {
+                time.time = NaiveTime::from_hms_opt(12, 0, 0).map(TimeOfDay::At)
+            }
+            ("on" | "the" | "of" | "today" | "tonight", _) => {}
+            _ => {
+                if let Ok(weekday) = word.parse::<Weekday>() {
+                    time.weekday = Some(weekday);
+                } else if let Ok(month) = word.parse::<Month>() {
+                    time.month = Some(month);
+                } else if let Some(day) = parse_day(word).filter(|_| {
+                    next.is_some_and(|n| n.parse::<Month>().is_ok())
+                        || (i > 0 && words[i - 1].parse::<Month>().is_ok())
+                }) {
+                    time.day = Some(day);
+                } else {
+                    return None;
+                }
+            }
+        }
+        i += 1;
+    }
+
+    (!time.is_empty()).then_some(time)
+}
+
+fn is_service_word(word: &str) -> bool {
+    matches!(
+        word,
+        "service" | "services" | "train" | "trains" | "tram" | "trams" | "bus" | "buses"
+    )
+}
+
+/// "4", "4th", "21st"
+fn parse_day(word: &str) -> Option<u32> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    digits.parse().ok().filter(|d| (1..=31).contains(d))
+}
+
+/// "8pm", "8:30pm", "8.30 pm", "20:30". Returns the time and the number of words it used
+fn parse_clock(word: &str, next: Option<&str>) -> Option<(NaiveTime, usize)> {
+    let (digits, meridiem, used) = if let Some(d) = word.strip_suffix("am") {
+        (d, Some(false), 1)
+    } else if let Some(d) = word.strip_suffix("pm") {
+        (d, Some(true), 1)
+    } else {
+        match next {
+            Some("am") => (word, Some(false), 2),
+            Some("pm") => (word, Some(true), 2),
+            _ => (word, None, 1),
+        }
+    };
+
+    let (hour, minute) = match digits.split_once([':', '.']) {
+        Some((h, m)) => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        // A lone number is a day of the month, not a time
+        None if meridiem.is_some() => (digits.parse::<u32>().ok()?, 0),
+        None => return None,
+    };
+
+    let hour = match meridiem {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(false) => hour % 12,
+        Some(true) => hour % 12 + 12,
+        None => hour,
+    };
+
+    Some((NaiveTime::from_hms_opt(hour, minute, 0)?, used))
+}
@@ -0,0 +1,213 @@
+//! Snapping vehicle positions onto route shapes, to say where along the line a vehicle is.
+
+use std::ops::Range;
+
+use geo_types::{Coord, Point};
+
+use crate::{
+    geometry::{coord, haversine_metres, project_onto_segment},
+    spatial::IndexedStop,
+    time::MelbourneTime,
+    ty::{Geopath, PatternResponse, Run, StoppingPatternsStop},
+};
+
+/// Segments heading away from the vehicle's bearing are treated as this much further away,
+/// so a vehicle on one track of a line isn't snapped onto the other
+const BEARING_PENALTY_METRES: f64 = 25.0;
+
+/// A path starting within this distance of where the previous one ends carries on from it
+const CONTIGUOUS_METRES: f64 = 1.0;
+
+/// Where a point lies along a geopath
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathPosition {
+    /// The nearest point on the path
+    pub point: Point<f64>,
+    /// Index into [`Geopath::paths`] of the path `point` is on
+    pub path: usize,
+    /// Distance along the line to `point`, in metres.
+    /// Paths that start where the previous one ends are measured as one line, but any other path
+    /// (a branch, or an overlap like the City Loop) is measured from its own start
+    pub distance_along: f64,
+    /// Distance from the original point to the path, in metres
+    pub cross_track_error: f64,
+}
+
+/// Where a run's vehicle is along its route
+#[derive(Debug, Clone, Copy)]
+pub struct RunPosition<'a> {
+    pub position: PathPosition,
+    /// The last stop of the pattern the vehicle has passed, None before the first stop
+    pub previous_stop: Option<&'a StoppingPatternsStop>,
+    /// The next stop of the pattern the vehicle will reach, None after the last stop
+    pub next_stop: Option<&'a StoppingPatternsStop>,
+    /// How far the vehicle is from the previous stop to the next, from 0 to 1.
+    /// None before the first stop or after the last
+    pub progress: Option<f64>,
+}
+
+fn angle_between(a: f64, b: f64) -> f64 {
+    let difference = (a - b).rem_euclid(360.0);
+    difference.min(360.0 - difference)
+}
+
+impl Geopath {
+    /// Runs of paths that each start where the one before ends, as ranges of indexes into `paths`
+    fn lines(&self) -> Vec<Range<usize>> {
+        let mut lines: Vec<Range<usize>> = Vec::new();
+        for (i, path) in self.paths.iter().enumerate() {
+            let previous_end = i.checked_sub(1).and_then(|i| self.paths[i].last());
+            let follows = previous_end.zip(path.first()).is_some_and(|(end, start)| {
+                haversine_metres(coord(end), coord(start)) < CONTIGUOUS_METRES
+            });
+            match lines.last_mut() {
+                Some(line) if follows => line.end = i + 1,
+                _ => lines.push(i..i + 1),
+            }
+        }
+        lines
+    }
+
+    /// The nearest point to `point` on one line of paths, with its score (the distance plus any bearing penalty)
+    fn snap_line(
+        &self,
+        point: Coord<f64>,
+        bearing: Option<f64>,
+        line: Range<usize>,
+    ) -> Option<(f64, PathPosition)> {
+        let mut travelled = 0.0;
+        let mut best: Option<(f64, PathPosition)> = None;
+
+        for (index, path) in self.paths[line.clone()].iter().enumerate() {
+            for segment in path.windows(2) {
+                let (start, end) = (coord(&segment[0]), coord(&segment[1]));
+                let (t, distance, heading) = project_onto_segment(point, start, end);
+                let length = haversine_metres(start, end);
+
+                let score = match bearing {
+                    Some(bearing) if angle_between(bearing, heading) > 90.0 => {
+                        distance + BEARING_PENALTY_METRES
+                    }
+                    _ => distance,
+                };
+                if best.is_none_or(|(best, _)| score < best) {
+                    let snapped = start + (end - start) * t;
+                    best = Some((
+                        score,
+                        PathPosition {
+                            point: snapped.into(),
+                            path: line.start + index,
+                            distance_along: travelled + length * t,
+                            cross_track_error: distance,
+                        },
+                    ));
+                }
+
+                travelled += length;
+            }
+        }
+
+        best
+    }
+
+    /// Project `point` onto the nearest segment of the path.
+    ///
+    /// With a `bearing` (degrees clockwise from north, as in [`VehiclePosition::bearing`](crate::ty::VehiclePosition::bearing)),
+    /// segments heading the same way as the vehicle are preferred.
+    /// None if the path has no segments
+    pub fn snap(&self, point: Point<f64>, bearing: Option<f64>) -> Option<PathPosition> {
+        self.lines()
+            .into_iter()
+            .filter_map(|line| self.snap_line(point.into(), bearing, line))
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, position)| position)
+    }
+}
+
+impl Run {
+    /// The geopath the run is following, from the run itself or else the route in `pattern`.
+    /// Prefers a path for the run's direction that's valid when the vehicle position was recorded
+    fn pattern_geopath<'a>(&'a self, pattern: &'a PatternResponse) -> Option<&'a Geopath> {
+        let geopaths = match self.geopath.as_slice() {
+            [] => pattern.routes.get(&self.route_id)?.geopath.as_deref()?,
+            geopaths => geopaths,
+        };
+        let date = self
+            .vehicle_position
+            .as_ref()
+            .map(|position| position.datetime.to_melbourne().date_naive());
+
+        let same_direction = |g: &&Geopath| g.direction_id == self.direction_id;
+        geopaths
+            .iter()
+            .filter(same_direction)
+            .find(|g| date.is_some_and(|date| g.is_valid_on(date)))
+            .or_else(|| geopaths.iter().find(same_direction))
+            .or_else(|| geopaths.first())
+    }
+
+    /// Snap the run's vehicle position onto its geopath, finding the stops of `pattern` either side of it.
+    ///
+    /// None without a vehicle position or a geopath, in either the run or the pattern's routes
+    /// (request the pattern with `include_geopath`)
+    pub fn snap_to_pattern<'a>(&'a self, pattern: &'a PatternResponse) -> Option<RunPosition<'a>> {
+        let vehicle = self.vehicle_position.as_ref()?;
+        let geopath = self.pattern_geopath(pattern)?;
+
+        let bearing = vehicle
+            .bearing
+            .and_then(|b| rust_decimal::prelude::ToPrimitive::to_f64(&b));
+        let position = geopath.snap(vehicle.coordinates()?, bearing)?;
+
+        // Stops in the order the run calls at them, with how far along the vehicle's line each is.
+        // Distances on other lines aren't comparable, so stops are snapped onto the same one
+        let line = geopath
+            .lines()
+            .into_iter()
+            .find(|line| line.contains(&position.path))?;
+        let mut departures = pattern
+            .departures
+            .iter()
+            .filter(|d| d.run_ref == self.run_ref)
+            .collect::<Vec<_>>();
+        departures.sort_by_key(|d| d.departure_sequence);
+        let stops = departures
+            .into_iter()
+            .filter_map(|d| pattern.stops.get(&d.stop_id))
+            .filter_map(|stop| {
+                let point = stop.location()?.into();
+                let (_, along) = geopath.snap_line(point, None, line.clone())?;
+                Some((stop, along.distance_along))
+            })
+            .collect::<Vec<_>>();
+
+        // The geopath might be drawn the opposite way to the run
+        let reversed = matches!(stops.as_slice(), [(_, first), .., (_, last)] if first > last);
+        let passed = |along: f64| match reversed {
+            false => along <= position.distance_along,
+            true => along >= position.distance_along,
+        };
+
+        let next = stops.iter().position(|(_, along)| !passed(*along));
+        let previous = match next {
+            Some(next) => next.checked_sub(1),
+            None => stops.len().checked_sub(1),
+        };
+
+        let progress = previous.zip(next).map(|(previous, next)| {
+            let (from, to) = (stops[previous].1, stops[next].1);
+            if to == from {
+                0.0
+            } else {
+                ((position.distance_along - from) / (to - from)).clamp(0.0, 1.0)
+            }
+        });
+
+        Some(RunPosition {
+            position,
+            previous_stop: previous.map(|i| stops[i].0),
+            next_stop: next.map(|i| stops[i].0),
+            progress,
+        })
+    }
+}
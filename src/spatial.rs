@@ -0,0 +1,203 @@
+//! Nearest stop queries over a local set of stops, without calling the API.
+
+use geo_types::Point;
+use rstar::{RTree, primitives::GeomWithData};
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::{
+    geometry::{EARTH_RADIUS, haversine_metres},
+    ty::{ResultStop, RouteType, Stop, StopDetails, StopId, StoppingPatternsStop},
+};
+
+/// Anything stop-like that can go in a [`StopIndex`]
+pub trait IndexedStop {
+    fn stop_id(&self) -> StopId;
+    fn route_type(&self) -> RouteType;
+    /// Where the stop is, None if the location wasn't included in the response or isn't a valid `f64`
+    fn location(&self) -> Option<Point<f64>>;
+}
+
+impl IndexedStop for Stop {
+    fn stop_id(&self) -> StopId {
+        self.id
+    }
+
+    fn route_type(&self) -> RouteType {
+        self.route_type
+    }
+
+    fn location(&self) -> Option<Point<f64>> {
+        Some(Point::new(
+            self.longitude.to_f64()?,
+            self.latitude.to_f64()?,
+        ))
+    }
+}
+
+impl IndexedStop for StoppingPatternsStop {
+    fn stop_id(&self) -> StopId {
+        self.stop.id
+    }
+
+    fn route_type(&self) -> RouteType {
+        self.stop.route_type
+    }
+
+    fn location(&self) -> Option<Point<f64>> {
+        self.stop.location()
+    }
+}
+
+impl IndexedStop for ResultStop {
+    fn stop_id(&self) -> StopId {
+        self.stop.id
+    }
+
+    fn route_type(&self) -> RouteType {
+        self.stop.route_type
+    }
+
+    fn location(&self) -> Option<Point<f64>> {
+        self.stop.location()
+    }
+}
+
+impl IndexedStop for StopDetails {
+    fn stop_id(&self) -> StopId {
+        self.id
+    }
+
+    fn route_type(&self) -> RouteType {
+        self.route_type
+    }
+
+    fn location(&self) -> Option<Point<f64>> {
+        let gps = &self.location.as_ref()?.gps;
+        Some(Point::new(gps.longitude.to_f64()?, gps.latitude.to_f64()?))
+    }
+}
+
+impl<T: IndexedStop> IndexedStop for &T {
+    fn stop_id(&self) -> StopId {
+        (*self).stop_id()
+    }
+
+    fn route_type(&self) -> RouteType {
+        (*self).route_type()
+    }
+
+    fn location(&self) -> Option<Point<f64>> {
+        (*self).location()
+    }
+}
+
+/// A stop found by a [`StopIndex`] query
+#[derive(Debug, Clone, Copy)]
+pub struct NearbyStop<'a, T> {
+    pub stop: &'a T,
+    /// Distance from the query point along the ground, in metres, as in [`Stop::distance`]
+    pub distance: f64,
+}
+
+/// A spatial index (R-tree) of stops for radius and nearest neighbour queries.
+///
+/// Stops are indexed as points on a sphere, so straight line distances in the tree
+/// order the same as distances along the ground, anywhere in the state.
+/// Stops without a location are left out.
+pub struct StopIndex<T> {
+    stops: Vec<T>,
+    tree: RTree<GeomWithData<[f64; 3], usize>>,
+}
+
+/// A point on a sphere the size of the Earth
+fn to_cartesian(point: Point<f64>) -> [f64; 3] {
+    let (latitude, longitude) = (point.y().to_radians(), point.x().to_radians());
+    [
+        EARTH_RADIUS * latitude.cos() * longitude.cos(),
+        EARTH_RADIUS * latitude.cos() * longitude.sin(),
+        EARTH_RADIUS * latitude.sin(),
+    ]
+}
+
+impl<T: IndexedStop> StopIndex<T> {
+    pub fn new(stops: impl IntoIterator<Item = T>) -> Self {
+        let stops = stops
+            .into_iter()
+            .filter(|stop| stop.location().is_some())
+            .collect::<Vec<_>>();
+        let tree = RTree::bulk_load(
+            stops
+                .iter()
+                .enumerate()
+                .filter_map(|(i, stop)| Some(GeomWithData::new(to_cartesian(stop.location()?), i)))
+                .collect(),
+        );
+        StopIndex { stops, tree }
+    }
+
+    /// Number of stops in the index
+    pub fn len(&self) -> usize {
+        self.stops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stops.is_empty()
+    }
+
+    /// Every stop in the index
+    pub fn stops(&self) -> &[T] {
+        &self.stops
+    }
+
+    fn nearby(&self, point: Point<f64>, i: usize) -> NearbyStop<'_, T> {
+        let stop = &self.stops[i];
+        NearbyStop {
+            stop,
+            distance: stop
+                .location()
+                .map_or(f64::INFINITY, |l| haversine_metres(point.0, l.0)),
+        }
+    }
+
+    /// Stops within `radius` metres of `point`, nearest first, optionally only those of `route_type`
+    pub fn within(
+        &self,
+        point: Point<f64>,
+        radius: f64,
+        route_type: Option<RouteType>,
+    ) -> Vec<NearbyStop<'_, T>> {
+        // The straight line (chord) distance for `radius` along the ground
+        let angle = (radius / (2.0 * EARTH_RADIUS)).min(std::f64::consts::FRAC_PI_2);
+        let chord = 2.0 * EARTH_RADIUS * angle.sin();
+        let mut stops = self
+            .tree
+            .locate_within_distance(to_cartesian(point), chord.powi(2))
+            .filter(|entry| route_type.is_none_or(|r| self.stops[entry.data].route_type() == r))
+            .map(|entry| self.nearby(point, entry.data))
+            .filter(|nearby| nearby.distance <= radius)
+            .collect::<Vec<_>>();
+        stops.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        stops
+    }
+
+    /// The `k` stops nearest to `point`, nearest first, optionally only those of `route_type`
+    pub fn nearest(
+        &self,
+        point: Point<f64>,
+        k: usize,
+        route_type: Option<RouteType>,
+    ) -> Vec<NearbyStop<'_, T>> {
+        self.tree
+            .nearest_neighbor_iter(to_cartesian(point))
+            .filter(|entry| route_type.is_none_or(|r| self.stops[entry.data].route_type() == r))
+            .take(k)
+            .map(|entry| self.nearby(point, entry.data))
+            .collect()
+    }
+}
+
+impl<T: IndexedStop> FromIterator<T> for StopIndex<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        StopIndex::new(iter)
+    }
+}
@@ -0,0 +1,52 @@
+//! The API speaks UTC, but timetables (and people) speak Melbourne time.
+//! These helpers convert between the two, taking AEST/AEDT into account.
+
+use chrono::{DateTime, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+
+use crate::ty::{Departure, Disruption};
+
+/// Australia/Melbourne, AEST (UTC+10) or AEDT (UTC+11) depending on daylight saving
+pub const MELBOURNE: Tz = chrono_tz::Australia::Melbourne;
+
+pub trait MelbourneTime {
+    /// Convert to Melbourne local time
+    fn to_melbourne(&self) -> DateTime<Tz>;
+}
+
+impl<T: TimeZone> MelbourneTime for DateTime<T> {
+    fn to_melbourne(&self) -> DateTime<Tz> {
+        self.with_timezone(&MELBOURNE)
+    }
+}
+
+/// Interpret a naive date and time as Melbourne local time.
+/// Times repeated when daylight saving ends resolve to the earlier of the two,
+/// times skipped when it starts return None.
+pub fn melbourne_local(date: NaiveDateTime) -> Option<DateTime<Tz>> {
+    MELBOURNE.from_local_datetime(&date).earliest()
+}
+
+impl Departure {
+    /// Scheduled departure in Melbourne local time
+    pub fn scheduled_departure_local(&self) -> Option<DateTime<Tz>> {
+        self.scheduled_departure.map(|d| d.to_melbourne())
+    }
+
+    /// Real-time estimated departure in Melbourne local time
+    pub fn estimated_departure_local(&self) -> Option<DateTime<Tz>> {
+        self.estimated_departure.map(|d| d.to_melbourne())
+    }
+}
+
+impl Disruption {
+    /// Start of the disruption in Melbourne local time
+    pub fn from_date_local(&self) -> DateTime<Tz> {
+        self.from_date.to_melbourne()
+    }
+
+    /// End of the disruption in Melbourne local time, if known
+    pub fn to_date_local(&self) -> Option<DateTime<Tz>> {
+        self.to_date.map(|d| d.to_melbourne())
+    }
+}
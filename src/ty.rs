@@ -4,19 +4,38 @@
 //!
 //! I appreciate any work done to fill in the TODO: T types.
 
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
 use derive_more::{Display, From};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::{collections::BTreeMap, str::FromStr};
-use to_and_fro::{output_case, ToAndFro};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+use to_and_fro::ToAndFro;
 
 use crate::helpers::{
-    de_iso_8601, de_rfc3339, de_service_time, deserialize_path, opt_de_rfc3339, opt_ser_rfc3339,
-    ser_disruption_query, ser_iso_8601, ser_rfc3339, ser_touch_utc,
+    de_rfc3339, de_service_time, de_timestamp, deserialize_path, opt_de_rfc3339, opt_de_timestamp,
+    opt_ser_rfc3339, ser_disruption_query, ser_iso_8601, ser_rfc3339, ser_touch_utc,
 };
 
+/// A date and time passed to the API in request options.
+///
+/// Build one from a [`DateTime`] in any time zone with `.into()`, e.g.
+/// `Some(Local::now().into())` or `Some(now.with_timezone(&Melbourne).into())`.
+/// The API only takes UTC, so the offset is dropped and the instant is kept.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RequestDate(pub DateTime<Utc>);
+
+impl<Tz: TimeZone> From<DateTime<Tz>> for RequestDate {
+    fn from(date: DateTime<Tz>) -> Self {
+        RequestDate(date.with_timezone(&Utc))
+    }
+}
+
+impl From<RequestDate> for DateTime<Utc> {
+    fn from(date: RequestDate) -> Self {
+        date.0
+    }
+}
+
 pub struct I32ButSilly(pub i32);
 impl<'de> Deserialize<'de> for I32ButSilly {
     fn deserialize<D>(deserializer: D) -> Result<I32ButSilly, D::Error>
@@ -42,6 +61,63 @@ macro_rules! newtype_i32 {
         pub struct $name(pub i32);
     };
 }
+/// `as_str`, `FromStr`, `Display` and serde for an enum of API strings with an `Other(String)` fallback.
+/// Each variant has the string it's sent as, then any aliases. Parsing ignores case and surrounding
+/// whitespace; anything unrecognised is kept as is in `Other`.
+macro_rules! string_enum {
+    ($name:ident { $($variant:ident => $str:literal $(| $alias:literal)*),* $(,)? }) => {
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $str,)*
+                    $name::Other(x) => x,
+                }
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = std::convert::Infallible;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let trimmed = s.trim();
+                $(
+                    if trimmed.eq_ignore_ascii_case($str)
+                        $(|| trimmed.eq_ignore_ascii_case($alias))*
+                    {
+                        return Ok($name::$variant);
+                    }
+                )*
+                Ok($name::Other(s.to_string()))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<$name, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let value = String::deserialize(deserializer)?;
+                Self::from_str(&value).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
 newtype_i32!(DisruptionId);
 
 newtype_i32!(RunId);
@@ -52,6 +128,53 @@ newtype_i32!(RouteId);
 
 newtype_i32!(DirectionId);
 
+/// Alphanumeric trip/service run identifier
+#[derive(Debug, Clone, Deserialize, Serialize, Display, From, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct RunRef(pub String);
+
+impl From<&str> for RunRef {
+    fn from(value: &str) -> Self {
+        RunRef(value.to_owned())
+    }
+}
+
+/// A latitude or longitude in a [`Geopath`]
+pub type Coordinate = Decimal;
+
+/// A latitude or longitude as an `f64`, totally ordered (see [`f64::total_cmp`]) so it can be compared like the other fields
+#[derive(Debug, Copy, Clone, Default, Deserialize, Serialize, Display, From)]
+#[serde(transparent)]
+pub struct Degrees(pub f64);
+
+impl PartialEq for Degrees {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Degrees {}
+
+impl PartialOrd for Degrees {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Degrees {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl FromStr for Degrees {
+    type Err = std::num::ParseFloatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Degrees)
+    }
+}
+
 /// Routepath (TODO)
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Geopath {
@@ -59,9 +182,22 @@ pub struct Geopath {
     pub valid_from: NaiveDate,
     pub valid_to: NaiveDate,
     #[serde(deserialize_with = "deserialize_path")]
-    pub paths: Vec<Vec<(Decimal, Decimal)>>,
+    pub paths: Vec<Vec<(Coordinate, Coordinate)>>,
 } // TODO: T
 
+/// A [`Geopath`] with its coordinates parsed straight to `f64`, several times quicker than [`Decimal`]
+/// for responses with include_geopath. Responses carrying geopaths take it as their type parameter
+/// (e.g. `PatternResponse<GeopathF64>`), and the `Client` methods ending `_f64` return them
+#[cfg(feature = "f64-geopath")]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GeopathF64 {
+    pub direction_id: DirectionId,
+    pub valid_from: NaiveDate,
+    pub valid_to: NaiveDate,
+    #[serde(deserialize_with = "crate::helpers::deserialize_path_f64")]
+    pub paths: Vec<Vec<(Degrees, Degrees)>>,
+}
+
 /// Types of routes
 #[derive(Debug, Copy, Clone, Display, From, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(i8)]
@@ -119,7 +255,7 @@ impl From<RouteType> for i8 {
     }
 }
 /// Modes of disruption
-#[derive(Debug, Serialize, Deserialize, Clone, From, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, From, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(tag = "disruption_mode_name", content = "disruption_mode")]
 #[repr(i8)]
 pub enum DisruptionModes {
@@ -154,6 +290,24 @@ pub enum DisruptionModes {
 }
 
 impl DisruptionModes {
+    /// Every mode we know about, in the order they're listed in [`Disruptions`]
+    pub const ALL: [DisruptionModes; 14] = [
+        DisruptionModes::General,
+        DisruptionModes::MetroTrain,
+        DisruptionModes::MetroTram,
+        DisruptionModes::MetroBus,
+        DisruptionModes::RegionalTrain,
+        DisruptionModes::RegionalCoach,
+        DisruptionModes::RegionalBus,
+        DisruptionModes::SchoolBus,
+        DisruptionModes::Telebus,
+        DisruptionModes::NightBus,
+        DisruptionModes::Ferry,
+        DisruptionModes::InterstateTrain,
+        DisruptionModes::Skybus,
+        DisruptionModes::Taxi,
+    ];
+
     pub fn as_number(&self) -> i8 {
         *self as i8
     }
@@ -186,7 +340,7 @@ pub struct DeparturesStopOpts {
     #[serde(serialize_with = "ser_iso_8601")]
     #[serde(rename = "date_utc")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub date: Option<NaiveDateTime>,
+    pub date: Option<RequestDate>,
     /// Maximum number of results returned
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_results: Option<i32>,
@@ -224,7 +378,7 @@ pub struct DeparturesStopRouteOpts {
     #[serde(serialize_with = "ser_iso_8601")]
     #[serde(rename = "date_utc")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub date: Option<NaiveDateTime>,
+    pub date: Option<RequestDate>,
     /// Maximum number of results returned
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_results: Option<i32>,
@@ -267,19 +421,19 @@ impl<'de> Deserialize<'de> for ExpandOptions {
     }
 }
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct DeparturesResponse {
+pub struct DeparturesResponse<G = Geopath> {
     /// Timetabled and real-time service departures
     pub departures: Vec<Departure>,
     /// A train station, tram stop, bus stop, regional coach stop or Night Bus stop
-    pub stops: BTreeMap<String, Stop>,
+    pub stops: BTreeMap<StopId, Stop>,
     /// Train lines, tram routes, bus routes, regional coach routes, Night Bus routes
-    pub routes: BTreeMap<String, RouteWithGeoPath>,
+    pub routes: BTreeMap<RouteId, RouteWithGeoPath<G>>,
     /// Individual trips/services of a route
-    pub runs: BTreeMap<String, Run>,
+    pub runs: BTreeMap<RunRef, Run<G>>,
     /// Directions of travel of route
-    pub directions: BTreeMap<String, Direction>,
+    pub directions: BTreeMap<DirectionId, Direction>,
     /// Disruption information applicable to relevant routes or stops
-    pub disruptions: BTreeMap<String, Disruption>,
+    pub disruptions: BTreeMap<DisruptionId, Disruption>,
     // API Status / Metadata
     pub status: Status,
 }
@@ -321,7 +475,7 @@ pub struct Departure {
     /// Numeric trip/service run identifier. Defaults to -1 when run identifier is Alphanumeric
     pub run_id: RunId,
     /// Alphanumeric trip/service run identifier
-    pub run_ref: String,
+    pub run_ref: RunRef,
     /// Direction of travel identifier
     pub direction_id: DirectionId,
     /// Disruption information identifier(s)
@@ -330,12 +484,12 @@ pub struct Departure {
     #[serde(deserialize_with = "opt_de_rfc3339")]
     #[serde(serialize_with = "opt_ser_rfc3339")]
     #[serde(rename = "scheduled_departure_utc")]
-    pub scheduled_departure: Option<NaiveDateTime>, // TODO: Seems to always be Some
+    pub scheduled_departure: Option<DateTime<Utc>>, // TODO: Seems to always be Some
     /// Real-time estimate of departure time and date
     #[serde(deserialize_with = "opt_de_rfc3339")]
     #[serde(serialize_with = "opt_ser_rfc3339")]
     #[serde(rename = "estimated_departure_utc")]
-    pub estimated_departure: Option<NaiveDateTime>,
+    pub estimated_departure: Option<DateTime<Utc>>,
     /// Indicates if the metropolitan train service is at the platform at the time of query.
     /// false for other modes
     pub at_platform: bool,
@@ -343,13 +497,158 @@ pub struct Departure {
     /// None for other modes
     pub platform_number: Option<String>,
     /// Flag indicating special condition for run
-    pub flags: String,
+    pub flags: DepartureFlags,
     /// Chronological sequence for the departures in a run.
     pub departure_sequence: i32,
 
     pub skipped_stops: Option<Vec<Stop>>,
 }
 
+/// A special condition for a run, as found in [`Departure::flags`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DepartureFlag {
+    /// RR: Reservations required
+    ReservationsRequired,
+    /// GC: Guaranteed connection
+    GuaranteedConnection,
+    /// DOO: Drop off only
+    DropOffOnly,
+    /// PUO: Pick up only
+    PickUpOnly,
+    /// MO: Mondays only
+    MondaysOnly,
+    /// TU: Tuesdays only
+    TuesdaysOnly,
+    /// WE: Wednesdays only
+    WednesdaysOnly,
+    /// TH: Thursdays only
+    ThursdaysOnly,
+    /// FR: Fridays only
+    FridaysOnly,
+    /// SS: School days only
+    SchoolDaysOnly,
+    /// S_WCA: Wheelchair accessible vehicle
+    WheelchairAccessible,
+    /// S_VTR: Vehicle type code
+    VehicleType,
+    /// Anything we don't know about yet. Named `Other` like the fallback of the other string enums
+    /// (e.g. [`DisruptionType::Other`]) rather than `Unknown`
+    #[doc(alias = "Unknown")]
+    Other(String),
+}
+
+string_enum!(DepartureFlag {
+    ReservationsRequired => "RR",
+    GuaranteedConnection => "GC",
+    DropOffOnly => "DOO",
+    PickUpOnly => "PUO",
+    MondaysOnly => "MO",
+    TuesdaysOnly => "TU",
+    WednesdaysOnly => "WE",
+    ThursdaysOnly => "TH",
+    FridaysOnly => "FR",
+    SchoolDaysOnly => "SS",
+    WheelchairAccessible => "S_WCA",
+    VehicleType => "S_VTR",
+});
+
+/// Parsed [`Departure::flags`], which keeps the original string around.
+///
+/// Flags are separated by underscores or pipes (e.g. "RR_GC" or "S_WCA|PUO"),
+/// except the ones that start with "S_". The docs say to ignore "E", so we do.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DepartureFlags {
+    raw: String,
+    flags: Vec<DepartureFlag>,
+}
+
+impl DepartureFlags {
+    /// The flags string as returned by the API
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DepartureFlag> {
+        self.flags.iter()
+    }
+
+    pub fn contains(&self, flag: &DepartureFlag) -> bool {
+        self.flags.contains(flag)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    pub fn is_reservation_required(&self) -> bool {
+        self.contains(&DepartureFlag::ReservationsRequired)
+    }
+
+    pub fn is_guaranteed_connection(&self) -> bool {
+        self.contains(&DepartureFlag::GuaranteedConnection)
+    }
+
+    pub fn is_drop_off_only(&self) -> bool {
+        self.contains(&DepartureFlag::DropOffOnly)
+    }
+
+    pub fn is_pick_up_only(&self) -> bool {
+        self.contains(&DepartureFlag::PickUpOnly)
+    }
+
+    pub fn is_school_days_only(&self) -> bool {
+        self.contains(&DepartureFlag::SchoolDaysOnly)
+    }
+
+    pub fn is_wheelchair_accessible(&self) -> bool {
+        self.contains(&DepartureFlag::WheelchairAccessible)
+    }
+}
+
+impl FromStr for DepartureFlags {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut flags = Vec::new();
+        let mut parts = s.split(['_', '|']).map(str::trim).filter(|x| !x.is_empty());
+        while let Some(part) = parts.next() {
+            let flag = match part {
+                e if e.eq_ignore_ascii_case("E") => continue,
+                s if s.eq_ignore_ascii_case("S") => match parts.next() {
+                    Some(next) => format!("S_{next}"),
+                    None => part.to_string(),
+                },
+                _ => part.to_string(),
+            };
+            flags.push(DepartureFlag::from_str(&flag)?);
+        }
+
+        Ok(DepartureFlags {
+            raw: s.to_string(),
+            flags,
+        })
+    }
+}
+
+impl Serialize for DepartureFlags {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for DepartureFlags {
+    fn deserialize<D>(deserializer: D) -> Result<DepartureFlags, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        Self::from_str(&value).map_err(serde::de::Error::custom)
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct StopTicket {
     pub ticket_type: String,
@@ -374,12 +673,13 @@ pub struct Route {
     pub gtfs_id: String,
 }
 
+/// A route and its geopath, if asked for. `G` is [`Geopath`] unless the response
+/// was deserialized with `GeopathF64` (with the `f64-geopath` feature)
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct RouteWithGeoPath {
+pub struct RouteWithGeoPath<G = Geopath> {
     #[serde(flatten)]
     pub route: Route,
-    /// TODO: T
-    pub geopath: Option<Vec<Geopath>>,
+    pub geopath: Option<Vec<G>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -473,38 +773,80 @@ pub struct DisruptionsResponse {
     pub status: Status,
 }
 
+/// Disruptions grouped by mode. Missing modes are treated as empty.
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Disruptions {
     /// Subset of disruption information applicable to multiple route_types
+    #[serde(default)]
     pub general: Vec<Disruption>,
     /// Subset of disruption information applicable to metropolitan train
+    #[serde(default)]
     pub metro_train: Vec<Disruption>,
     /// Subset of disruption information applicable to metropolitan tram
+    #[serde(default)]
     pub metro_tram: Vec<Disruption>,
     /// Subset of disruption information applicable to metropolitan bus
+    #[serde(default)]
     pub metro_bus: Vec<Disruption>,
     /// Subset of disruption information applicable to V/Line train
+    #[serde(default)]
     pub regional_train: Vec<Disruption>,
     /// Subset of disruption information applicable to V/Line coach
+    #[serde(default)]
     pub regional_coach: Vec<Disruption>,
     /// Subset of disruption information applicable to regional bus
+    #[serde(default)]
     pub regional_bus: Vec<Disruption>,
     /// Subset of disruption information applicable to school bus
+    #[serde(default)]
     pub school_bus: Vec<Disruption>,
     /// Subset of disruption information applicable to telebus services
+    #[serde(default)]
     pub telebus: Vec<Disruption>,
     /// Subset of disruption information applicable to night bus
+    #[serde(default)]
     pub night_bus: Vec<Disruption>,
     /// Subset of disruption information applicable to ferry
+    #[serde(default)]
     pub ferry: Vec<Disruption>,
     /// Subset of disruption information applicable to interstate train
+    #[serde(default)]
     pub interstate_train: Vec<Disruption>,
     /// Subset of disruption information applicable to skybus
+    #[serde(default)]
     pub skybus: Vec<Disruption>,
     /// Subset of disruption information applicable to taxi
+    #[serde(default)]
     pub taxi: Vec<Disruption>,
+    /// Modes the API has added since this was written, keyed by name
+    #[serde(flatten)]
+    pub other: BTreeMap<String, Vec<Disruption>>,
 }
 
+/// Type of disruption, ordered from least to most severe.
+/// Types we don't know about sort below everything else.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DisruptionType {
+    Other(String),
+    ServiceInformation,
+    PlannedWorks,
+    MinorDelays,
+    MajorDelays,
+    PlannedClosure,
+    PartSuspended,
+    Suspended,
+}
+
+string_enum!(DisruptionType {
+    ServiceInformation => "Service Information",
+    PlannedWorks => "Planned Works",
+    MinorDelays => "Minor Delays",
+    MajorDelays => "Major Delays",
+    PlannedClosure => "Planned Closure",
+    PartSuspended => "Part Suspended",
+    Suspended => "Suspended",
+});
+
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Disruption {
     /// Disruption information identifier
@@ -518,23 +860,23 @@ pub struct Disruption {
     /// Status of the disruption (e.g. "Planned", "Current")
     pub disruption_status: DisruptionStatus, // TODO: This might want to be a String
     /// Type of disruption
-    pub disruption_type: String,
+    pub disruption_type: DisruptionType,
     /// Date and time disruption information is published on PTV website
     #[serde(deserialize_with = "de_rfc3339")]
     #[serde(serialize_with = "ser_rfc3339")]
-    pub published_on: NaiveDateTime,
+    pub published_on: DateTime<Utc>,
     /// Date and time disruption information was last updated by PTV
     #[serde(deserialize_with = "de_rfc3339")]
     #[serde(serialize_with = "ser_rfc3339")]
-    pub last_updated: NaiveDateTime,
+    pub last_updated: DateTime<Utc>,
     /// Date and time at which disruption begins
     #[serde(deserialize_with = "de_rfc3339")]
     #[serde(serialize_with = "ser_rfc3339")]
-    pub from_date: NaiveDateTime,
+    pub from_date: DateTime<Utc>,
     /// Date and time at which disruption ends (returns None if unknown)
     #[serde(deserialize_with = "opt_de_rfc3339")]
     #[serde(serialize_with = "opt_ser_rfc3339")]
-    pub to_date: Option<NaiveDateTime>,
+    pub to_date: Option<DateTime<Utc>>,
     /// Route relevant to a disruption (if applicable)
     pub routes: Vec<DisruptionRoute>,
     /// Stop relevant to a disruption (if applicable)
@@ -572,10 +914,11 @@ pub struct DisruptionDirection {
     #[serde(rename = "direction_name")]
     pub name: String,
     /// Time of service to which disruption applies. Returns None if disruption applies to multiple, or no services
+    /// This is Melbourne local time (AEST/AEDT), not UTC.
     ///
     /// This doesn't use null, it uses a blank string. I hate it here.
     #[serde(deserialize_with = "de_service_time")]
-    pub service_time: Option<NaiveDateTime>,
+    pub service_time: Option<NaiveTime>,
 }
 
 //
@@ -586,12 +929,12 @@ pub struct FareEstimateOpts {
     #[serde(serialize_with = "ser_touch_utc")]
     #[serde(rename = "journey_touch_on_utc")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub touch_on: Option<NaiveDateTime>,
+    pub touch_on: Option<RequestDate>,
     /// Journey touch off
     #[serde(serialize_with = "ser_touch_utc")]
     #[serde(rename = "journey_touch_off_utc")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub touch_off: Option<NaiveDateTime>,
+    pub touch_off: Option<RequestDate>,
     #[serde(rename = "is_journey_in_free_tram_zone")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub free_tram_zone: Option<bool>,
@@ -741,7 +1084,7 @@ pub struct PatternsRunRouteOpts {
     #[serde(serialize_with = "ser_iso_8601")]
     #[serde(rename = "date_utc")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub date: Option<NaiveDateTime>,
+    pub date: Option<RequestDate>,
     /// Include any skipped stops in a stopping pattern
     /// (default = false)
     #[serde(rename = "include_skipped_stops")]
@@ -753,20 +1096,20 @@ pub struct PatternsRunRouteOpts {
     pub include_geopath: Option<bool>,
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)] // PartialOrd, Ord can be added once Value has a strong type
-pub struct PatternResponse {
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PatternResponse<G = Geopath> {
     /// Disruption information applicable to relevant routes or stops
     pub disruptions: Vec<Disruption>,
     /// Timetabled and real-time service departures
     pub departures: Vec<Departure>,
     /// A train station, tram stop, bus stop, regional coach stop or Night Bus stop
-    pub stops: BTreeMap<String, StoppingPatternsStop>,
+    pub stops: BTreeMap<StopId, StoppingPatternsStop>,
     /// Train lines, tram routes, bus routes, regional coach routes, Night Bus routes
-    pub routes: BTreeMap<String, Value>, // TODO needs to be more specific
+    pub routes: BTreeMap<RouteId, RouteWithGeoPath<G>>,
     /// Individual trips/services of a route
-    pub runs: BTreeMap<String, Run>,
+    pub runs: BTreeMap<RunRef, Run<G>>,
     /// Directions of travel of route
-    pub directions: BTreeMap<String, Direction>,
+    pub directions: BTreeMap<DirectionId, Direction>,
     /// API Status / Metadata
     pub status: Status,
 }
@@ -787,10 +1130,11 @@ pub struct RouteIdOpts {
     /// Indicates kif geopath will be returned (default = false)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub include_geopath: Option<bool>,
-    // Filter geopath by date (default = current date)
+    /// Filter geopath by date (default = current date)
     #[serde(serialize_with = "ser_iso_8601")]
+    #[serde(rename = "date_utc")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub date: Option<NaiveDateTime>,
+    pub date: Option<RequestDate>,
 }
 
 /// This is just documented wrong?
@@ -804,26 +1148,29 @@ pub struct RoutesResponse {
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct RoutesIdResponse {
+pub struct RoutesIdResponse<G = Geopath> {
     /// Train lines, tram routes, bus routes, regional coach routes, Night Bus routes
-    pub route: Option<RouteWithStatus>,
+    pub route: Option<RouteWithStatus<G>>,
     /// API Status / Metadata
     pub status: Status,
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct RouteWithStatus {
+pub struct RouteWithStatus<G = Geopath> {
     /// Service status for the route (indicates disruptions)
     #[serde(rename = "route_service_status")]
     pub service_status: RouteServiceStatus,
     #[serde(flatten)]
-    pub route: RouteWithGeoPath,
+    pub route: RouteWithGeoPath<G>,
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RouteServiceStatus {
     pub description: String,
-    pub timestamp: String, // TODO: Add a deser. No information in docs.
+    /// When the status was last updated
+    #[serde(deserialize_with = "de_timestamp")]
+    #[serde(serialize_with = "ser_rfc3339")]
+    pub timestamp: DateTime<Utc>,
 }
 
 #[derive(Serialize, Default)]
@@ -835,7 +1182,7 @@ pub struct RunsIdOpts {
     #[serde(serialize_with = "ser_iso_8601")]
     #[serde(rename = "date_utc")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub date: Option<NaiveDateTime>,
+    pub date: Option<RequestDate>,
 }
 
 #[derive(Serialize, Default)]
@@ -847,27 +1194,44 @@ pub struct RunsRefOpts {
     #[serde(serialize_with = "ser_iso_8601")]
     #[serde(rename = "date_utc")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub date: Option<NaiveDateTime>,
+    pub date: Option<RequestDate>,
     /// Indicates if the route geopath should be returned
     #[serde(skip_serializing_if = "Option::is_none")]
     pub include_geopath: Option<bool>,
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct RunsResponse {
+pub struct RunsResponse<G = Geopath> {
     /// Individual trips/services of a route
-    pub runs: Vec<Run>,
+    pub runs: Vec<Run<G>>,
     /// API Status / Metadata
     pub status: Status,
 }
 
+/// Status of a run
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RunStatus {
+    Scheduled,
+    Updated,
+    Added,
+    Cancelled,
+    Other(String),
+}
+
+string_enum!(RunStatus {
+    Scheduled => "scheduled",
+    Updated => "updated",
+    Added => "added",
+    Cancelled => "cancelled",
+});
+
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Run {
+pub struct Run<G = Geopath> {
     /// Numeric trip/service run identifier.
     /// Defaults to -1 when run identifier is Alphanumeric
     pub run_id: RunId,
     /// Alphanumeric trip/service run identifier
-    pub run_ref: String,
+    pub run_ref: RunRef,
     /// Route identifier
     pub route_id: RouteId,
     /// Transport mode identifier
@@ -877,7 +1241,7 @@ pub struct Run {
     /// Name of destination of run
     pub destination_name: String,
     /// Status of metropolitan train run; returns "scheduled" for other modes
-    pub status: String,
+    pub status: RunStatus,
     /// Direction of travel identifier
     pub direction_id: DirectionId,
     /// Chronological sequence of the trip/service run on the route in direction
@@ -892,7 +1256,7 @@ pub struct Run {
     // Descriptor of the trip/service run. Only available for some runs.
     pub vehicle_descriptor: Option<VehicleDescriptor>,
     /// Geopath of the route
-    pub geopath: Vec<Geopath>,
+    pub geopath: Vec<G>,
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -913,24 +1277,50 @@ pub struct VehiclePosition {
     /// Supplier of the vehicle position data
     pub supplier: String,
     /// Date and time that the vehicle position data was supplied
-    #[serde(deserialize_with = "de_iso_8601")]
+    #[serde(deserialize_with = "de_timestamp")]
+    #[serde(serialize_with = "ser_rfc3339")]
     #[serde(rename = "datetime_utc")]
-    pub datetime: NaiveDateTime,
+    pub datetime: DateTime<Utc>,
     /// CIS - Metro Train Vehicle Location data expiry time
-    pub expiry_time: Option<String>, // TODO: Add a deser. No information in docs.
+    #[serde(default)]
+    #[serde(deserialize_with = "opt_de_timestamp")]
+    #[serde(serialize_with = "opt_ser_rfc3339")]
+    pub expiry_time: Option<DateTime<Utc>>,
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// Operator of a vehicle. Anything not listed here ends up in Other.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ServiceOperator {
-    #[serde(rename = "Metro Trains Melbourne")]
     MetroTrainsMelbourne,
-    #[serde(rename = "Yarra Trams")]
     YarraTrams,
-    #[serde(rename = "Ventura Bus Line")]
+    VLine,
     VenturaBusLine,
+    Kinetic,
+    CdcVictoria,
+    Transdev,
+    Dysons,
+    McHarrysBuslines,
+    SitaBuslines,
+    ChristiansBusCo,
+    Skybus,
     Other(String),
 }
 
+string_enum!(ServiceOperator {
+    MetroTrainsMelbourne => "Metro Trains Melbourne" | "Metro Trains" | "Metro",
+    YarraTrams => "Yarra Trams",
+    VLine => "V/Line" | "VLine",
+    VenturaBusLine => "Ventura Bus Line" | "Ventura Bus Lines" | "Ventura",
+    Kinetic => "Kinetic" | "Kinetic Melbourne",
+    CdcVictoria => "CDC Victoria" | "CDC" | "CDC Melbourne",
+    Transdev => "Transdev" | "Transdev Melbourne",
+    Dysons => "Dysons" | "Dyson Group",
+    McHarrysBuslines => "McHarry's Buslines" | "McHarrys Buslines" | "McHarry's",
+    SitaBuslines => "Sita Buslines" | "Sita",
+    ChristiansBusCo => "Christian's Bus Co" | "Christians Bus Co" | "Christian's Bus Company",
+    Skybus => "SkyBus",
+});
+
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct VehicleDescriptor {
     /// Operator name of the vehicle such as "Metro Trains Melbourne", "Yarra Trams", "Ventura Bus Line", etc.
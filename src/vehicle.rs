@@ -0,0 +1,232 @@
+//! Helpers for vehicle positions and descriptors attached to runs.
+
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use geo_types::Point;
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::{
+    projection::Grid,
+    ty::{RouteType, Run, VehicleDescriptor, VehiclePosition},
+    view::DepartureView,
+};
+
+impl VehiclePosition {
+    /// Whether the position is past its expiry time as of `now`.
+    /// Positions without an expiry time are never considered stale
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        self.expiry_time.is_some_and(|expiry| expiry <= now)
+    }
+
+    /// The vehicle's location, from its latitude and longitude or else
+    /// converted from its CIS easting and northing (see [`Grid`])
+    pub fn coordinates(&self) -> Option<Point<f64>> {
+        if let (Some(latitude), Some(longitude)) = (self.latitude, self.longitude) {
+            return Some(Point::new(longitude.to_f64()?, latitude.to_f64()?));
+        }
+
+        let (easting, northing) = (self.easting?.to_f64()?, self.northing?.to_f64()?);
+        Some(Grid::detect(easting, northing)?.to_wgs84(easting, northing))
+    }
+}
+
+/// Train and tram fleets, as found in [`VehicleDescriptor::description`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VehicleModel {
+    /// Comeng (metro train)
+    Comeng,
+    /// Siemens Nexas (metro train)
+    Siemens,
+    /// Alstom X'Trapolis (metro train)
+    XTrapolis,
+    /// High Capacity Metro Train
+    Hcmt,
+    /// VLocity (V/Line DMU)
+    VLocity,
+    /// N-class locomotive hauled carriages (V/Line)
+    NClass,
+    /// E-class tram
+    EClass,
+    /// D-class (Combino) tram
+    DClass,
+    /// C-class (Citadis) tram
+    CClass,
+    /// B-class tram
+    BClass,
+    /// Z-class tram
+    ZClass,
+    /// A-class tram
+    AClass,
+}
+
+impl VehicleModel {
+    pub fn route_type(&self) -> RouteType {
+        match self {
+            VehicleModel::Comeng
+            | VehicleModel::Siemens
+            | VehicleModel::XTrapolis
+            | VehicleModel::Hcmt => RouteType::Train,
+            VehicleModel::VLocity | VehicleModel::NClass => RouteType::VLine,
+            _ => RouteType::Tram,
+        }
+    }
+
+    /// Whether the vehicle is low floor. None for trains, where it doesn't apply
+    pub fn is_low_floor(&self) -> Option<bool> {
+        match self {
+            VehicleModel::EClass | VehicleModel::DClass | VehicleModel::CClass => Some(true),
+            VehicleModel::BClass | VehicleModel::ZClass | VehicleModel::AClass => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Whether the vehicle can be boarded without steps, from a level access stop or platform
+    pub fn is_step_free(&self) -> bool {
+        match self {
+            VehicleModel::Comeng
+            | VehicleModel::Siemens
+            | VehicleModel::XTrapolis
+            | VehicleModel::Hcmt
+            | VehicleModel::VLocity => true,
+            VehicleModel::NClass => false,
+            tram => tram.is_low_floor().unwrap_or_default(),
+        }
+    }
+
+    /// Rough length of a single car, in metres. Only known for trains
+    pub fn car_length_metres(&self) -> Option<f64> {
+        match self {
+            VehicleModel::Comeng => Some(23.8),
+            VehicleModel::Siemens => Some(24.2),
+            VehicleModel::XTrapolis => Some(23.2),
+            VehicleModel::Hcmt => Some(22.9),
+            VehicleModel::VLocity => Some(25.7),
+            VehicleModel::NClass => Some(23.5),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for VehicleModel {
+    type Err = String;
+
+    /// Parse the model out of a description like "6 Car Comeng", "7 Car HCMT" or "E-Class"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = description_words(s);
+        let compact = words.concat();
+
+        let train = [
+            ("comeng", VehicleModel::Comeng),
+            ("siemens", VehicleModel::Siemens),
+            ("xtrapolis", VehicleModel::XTrapolis),
+            ("hcmt", VehicleModel::Hcmt),
+            ("highcapacity", VehicleModel::Hcmt),
+            ("vlocity", VehicleModel::VLocity),
+        ]
+        .into_iter()
+        .find(|(name, _)| compact.contains(name));
+        if let Some((_, model)) = train {
+            return Ok(model);
+        }
+
+        // "E-Class", "D2", "Z3 class" etc.
+        let class = words.iter().enumerate().find_map(|(i, word)| {
+            let mut chars = word.chars();
+            let letter = chars.next().filter(char::is_ascii_alphabetic)?;
+            let rest = chars.as_str();
+            let is_class = (rest.is_empty() && words.get(i + 1).is_some_and(|w| w == "class"))
+                || (!rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()));
+            is_class.then_some(letter)
+        });
+
+        match class {
+            Some('n') => Ok(VehicleModel::NClass),
+            Some('e') => Ok(VehicleModel::EClass),
+            Some('d') => Ok(VehicleModel::DClass),
+            Some('c') => Ok(VehicleModel::CClass),
+            Some('b') => Ok(VehicleModel::BClass),
+            Some('z') => Ok(VehicleModel::ZClass),
+            Some('a') => Ok(VehicleModel::AClass),
+            _ => Err(format!("Unknown vehicle model '{s}'")),
+        }
+    }
+}
+
+fn description_words(s: &str) -> Vec<String> {
+    s.to_lowercase()
+        .replace(['\'', '\u{2019}'], "")
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl VehicleDescriptor {
+    /// The fleet the vehicle belongs to, parsed from the description
+    pub fn model(&self) -> Option<VehicleModel> {
+        self.description.as_deref()?.parse().ok()
+    }
+
+    /// Number of cars, parsed from descriptions like "6 Car Comeng"
+    pub fn car_count(&self) -> Option<u8> {
+        let words = description_words(self.description.as_deref()?);
+        words.iter().enumerate().find_map(|(i, word)| {
+            if let Some(count) = word.strip_suffix("car") {
+                return count.parse().ok();
+            }
+            words
+                .get(i + 1)
+                .filter(|next| next.starts_with("car"))
+                .and_then(|_| word.parse().ok())
+        })
+    }
+
+    /// Length of the vehicle in metres.
+    /// Uses the reported length if there is one, otherwise estimates it from the model and car count
+    pub fn length_metres(&self) -> Option<f64> {
+        if let Some(length) = self
+            .length
+            .as_deref()
+            .and_then(|l| l.trim().trim_end_matches('m').trim().parse::<f64>().ok())
+        {
+            return Some(length);
+        }
+
+        Some(self.model()?.car_length_metres()? * f64::from(self.car_count()?))
+    }
+
+    /// Whether the vehicle is low floor, as reported or derived from the model
+    pub fn is_low_floor(&self) -> Option<bool> {
+        self.low_floor
+            .or_else(|| self.model().and_then(|m| m.is_low_floor()))
+    }
+
+    /// Whether the vehicle can be boarded without steps. None if we don't know the model
+    pub fn is_step_free(&self) -> Option<bool> {
+        match self.low_floor {
+            Some(true) => Some(true),
+            _ => self.model().map(|m| m.is_step_free()),
+        }
+    }
+}
+
+impl Run {
+    /// Whether the vehicle running this service is low floor, when known
+    pub fn is_low_floor(&self) -> Option<bool> {
+        self.vehicle_descriptor.as_ref()?.is_low_floor()
+    }
+
+    /// Whether the vehicle running this service can be boarded without steps, when known
+    pub fn is_step_free(&self) -> Option<bool> {
+        self.vehicle_descriptor.as_ref()?.is_step_free()
+    }
+}
+
+impl<S, R> DepartureView<'_, S, R> {
+    /// Whether the departure's vehicle can be boarded without steps.
+    /// None unless the run was expanded with its vehicle descriptor
+    pub fn is_step_free(&self) -> Option<bool> {
+        self.run?.is_step_free()
+    }
+}
@@ -0,0 +1,61 @@
+//! Joined "views" over expanded responses.
+//!
+//! Responses like [`DeparturesResponse`] return the departures alongside separate
+//! maps of stops, routes, runs, directions and disruptions. These views do the
+//! lookups for you, so each departure comes with everything it references.
+
+use crate::ty::{
+    Departure, DeparturesResponse, Direction, Disruption, PatternResponse, RouteWithGeoPath, Run,
+    Stop, StoppingPatternsStop,
+};
+
+/// A departure joined with the expanded objects it references.
+/// Any of these will be None if the related object wasn't expanded in the response.
+#[derive(Debug)]
+pub struct DepartureView<'a, S = Stop, R = RouteWithGeoPath> {
+    pub departure: &'a Departure,
+    pub stop: Option<&'a S>,
+    pub route: Option<&'a R>,
+    pub run: Option<&'a Run>,
+    pub direction: Option<&'a Direction>,
+    pub disruptions: Vec<&'a Disruption>,
+}
+
+/// A departure within a stopping pattern, joined with the expanded objects it references.
+pub type PatternDepartureView<'a> = DepartureView<'a, StoppingPatternsStop>;
+
+impl DeparturesResponse {
+    /// Iterate over departures, joined with their stop, route, run, direction and disruptions
+    pub fn iter_resolved(&self) -> impl Iterator<Item = DepartureView<'_>> {
+        self.departures.iter().map(|departure| DepartureView {
+            departure,
+            stop: self.stops.get(&departure.stop_id),
+            route: self.routes.get(&departure.route_id),
+            run: self.runs.get(&departure.run_ref),
+            direction: self.directions.get(&departure.direction_id),
+            disruptions: departure
+                .disruption_ids
+                .iter()
+                .filter_map(|id| self.disruptions.get(id))
+                .collect(),
+        })
+    }
+}
+
+impl PatternResponse {
+    /// Iterate over the departures of the pattern, joined with their stop, route, run, direction and disruptions
+    pub fn iter_resolved(&self) -> impl Iterator<Item = PatternDepartureView<'_>> {
+        self.departures.iter().map(|departure| DepartureView {
+            departure,
+            stop: self.stops.get(&departure.stop_id),
+            route: self.routes.get(&departure.route_id),
+            run: self.runs.get(&departure.run_ref),
+            direction: self.directions.get(&departure.direction_id),
+            disruptions: departure
+                .disruption_ids
+                .iter()
+                .filter_map(|id| self.disruptions.iter().find(|d| d.disruption_id == *id))
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,1212 @@
+//! Deserialization tests against sample API responses in `tests/fixtures`
+//!
+//! `pattern_train_position.json` is `pattern_train.json` with a vehicle position for run 951002,
+//! and `pattern_train_skipped.json` adds the stops skipped on the way to Camberwell on top of that.
+
+use ptv::*;
+
+fn load<T: serde::de::DeserializeOwned>(json: &str) -> T {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+pub fn pattern_train() {
+    let pattern: PatternResponse = load(include_str!("fixtures/pattern_train.json"));
+
+    let route = &pattern.routes[&RouteId(1)];
+    assert_eq!(route.route.gtfs_id, "2-ALM");
+    let geopath = route.geopath.as_ref().unwrap();
+    assert_eq!(geopath[0].direction_id, DirectionId(1));
+    assert_eq!(geopath[0].paths[0].len(), 11);
+
+    let views = pattern.iter_resolved().collect::<Vec<_>>();
+    assert_eq!(views.len(), 3);
+    assert_eq!(views[0].stop.unwrap().stop.name, "Alamein");
+    assert_eq!(views[0].route.unwrap().route.name, "Alamein");
+    assert_eq!(
+        views[0].disruptions[0].disruption_type,
+        DisruptionType::PlannedWorks
+    );
+    assert!(views[2].disruptions.is_empty());
+}
+
+#[test]
+pub fn departures_resolved() {
+    let departures: DeparturesResponse = load(include_str!("fixtures/departures_train.json"));
+
+    let views = departures.iter_resolved().collect::<Vec<_>>();
+    assert_eq!(views.len(), 3);
+    let view = &views[1];
+    assert_eq!(view.departure.stop_id, StopId(1012));
+    assert_eq!(view.stop.unwrap().id, StopId(1012));
+    assert_eq!(view.route.unwrap().route.gtfs_id, "2-ALM");
+    assert_eq!(view.run.unwrap().destination_name, "Flinders Street");
+    assert_eq!(view.direction.unwrap().name, "City (Flinders Street)");
+    assert_eq!(view.disruptions.len(), 1);
+    assert_eq!(view.disruptions[0].disruption_id, DisruptionId(312001));
+    assert!(views[0].disruptions.is_empty());
+}
+
+#[test]
+pub fn departure_times() {
+    use chrono::{DateTime, Duration, Utc};
+
+    let mut departures: DeparturesResponse = load(include_str!("fixtures/departures_train.json"));
+    let at = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+
+    let first = &departures.departures[0];
+    assert_eq!(first.delay(), Some(Duration::minutes(1)));
+    assert_eq!(
+        first.effective_departure(),
+        Some(at("2024-05-02T08:03:00Z"))
+    );
+    assert_eq!(first.minutes_until(at("2024-05-02T08:00:00Z")), Some(3));
+    // Departed once the estimate is reached, not the timetabled time
+    assert!(!first.is_departed(at("2024-05-02T08:02:59Z")));
+    assert!(first.is_departed(at("2024-05-02T08:03:00Z")));
+    // No estimate: no delay or punctuality, falls back to the timetable
+    let scheduled = &departures.departures[2];
+    assert!(!scheduled.is_realtime());
+    assert_eq!(scheduled.delay(), None);
+    assert_eq!(
+        scheduled.punctuality(&PunctualityThresholds::default()),
+        None
+    );
+    assert_eq!(
+        scheduled.effective_departure(),
+        Some(at("2024-05-04T12:00:00Z"))
+    );
+
+    // Each threshold is inclusive
+    let thresholds = PunctualityThresholds::default();
+    let departure = &mut departures.departures[0];
+    let scheduled = departure.scheduled_departure.unwrap();
+    for (delay, punctuality) in [
+        (-60, Punctuality::Early),
+        (-59, Punctuality::OnTime),
+        (0, Punctuality::OnTime),
+        (299, Punctuality::OnTime),
+        (300, Punctuality::Late),
+        (899, Punctuality::Late),
+        (900, Punctuality::VeryLate),
+    ] {
+        departure.estimated_departure = Some(scheduled + Duration::seconds(delay));
+        assert_eq!(departure.punctuality(&thresholds), Some(punctuality));
+    }
+    departure.estimated_departure = Some(at("2024-05-02T08:03:00Z"));
+
+    let now = at("2024-05-02T08:04:00Z");
+    let upcoming = departures.upcoming(now);
+    assert_eq!(upcoming.len(), 2);
+    assert_eq!(upcoming[0].stop_id, StopId(1012));
+    assert_eq!(upcoming[1].run_ref, RunRef::from("951040"));
+
+    // Split the later run onto the other direction and an unknown platform
+    departures.departures[2].direction_id = DirectionId(2);
+    departures.departures[2].platform_number = None;
+    let earlier = at("2024-05-02T08:00:00Z");
+    let by_direction = departures.next_per_direction(earlier, 1);
+    assert_eq!(by_direction.len(), 2);
+    assert_eq!(by_direction[&DirectionId(1)][0].stop_id, StopId(1002));
+    assert_eq!(by_direction[&DirectionId(1)].len(), 1);
+    assert_eq!(
+        by_direction[&DirectionId(2)][0].run_ref,
+        RunRef::from("951040")
+    );
+    let by_platform = departures.next_per_platform(earlier, 5);
+    assert_eq!(by_platform[&Some("1".to_string())].len(), 2);
+    assert_eq!(by_platform[&None].len(), 1);
+    assert!(departures.next_per_direction(earlier, 0).is_empty());
+    assert!(departures.next_per_platform(now, 0).is_empty());
+}
+
+#[test]
+pub fn departure_flags() {
+    use DepartureFlag::*;
+
+    for (raw, expected) in [
+        ("RR_GC", vec![ReservationsRequired, GuaranteedConnection]),
+        ("S_WCA|PUO", vec![WheelchairAccessible, PickUpOnly]),
+        ("E_S_VTR", vec![VehicleType]),
+        ("s_wca|e|puo", vec![WheelchairAccessible, PickUpOnly]),
+        ("DOO_S", vec![DropOffOnly, Other("S".to_string())]),
+        ("", vec![]),
+        ("XYZ", vec![Other("XYZ".to_string())]),
+    ] {
+        let flags = raw.parse::<DepartureFlags>().unwrap();
+        assert_eq!(flags.iter().cloned().collect::<Vec<_>>(), expected, "{raw}");
+        assert_eq!(flags.as_str(), raw);
+    }
+
+    let flags = "RR_GC".parse::<DepartureFlags>().unwrap();
+    assert!(flags.is_reservation_required());
+    assert!(flags.is_guaranteed_connection());
+    assert!(!flags.is_pick_up_only());
+    assert!("".parse::<DepartureFlags>().unwrap().is_empty());
+    assert_eq!(
+        load::<DepartureFlags>(r#""S_WCA|PUO""#).as_str(),
+        "S_WCA|PUO"
+    );
+}
+
+#[test]
+pub fn pattern_tram() {
+    let pattern: PatternResponse = load(include_str!("fixtures/pattern_tram.json"));
+
+    let route = &pattern.routes[&RouteId(1083)];
+    assert_eq!(route.route.number, "19");
+    assert_eq!(route.geopath, Some(vec![]));
+
+    let run = &pattern.runs[&RunRef::from("60871")];
+    let descriptor = run.vehicle_descriptor.as_ref().unwrap();
+    assert_eq!(descriptor.operator, Some(ServiceOperator::YarraTrams));
+    assert_eq!(descriptor.low_floor, Some(true));
+}
+
+#[test]
+pub fn runs_train() {
+    let runs: RunsResponse = load(include_str!("fixtures/runs_train.json"));
+
+    let run = &runs.runs[0];
+    assert_eq!(run.status, RunStatus::Updated);
+
+    let position = run.vehicle_position.as_ref().unwrap();
+    let expiry = position.expiry_time.unwrap();
+    assert_eq!(expiry.to_rfc3339(), "2024-05-02T08:08:41+00:00");
+    assert!(!position.is_stale(position.datetime));
+    assert!(position.is_stale(expiry));
+}
+
+#[test]
+pub fn routes_id() {
+    let routes: RoutesIdResponse = load(include_str!("fixtures/routes_id.json"));
+
+    let status = routes.route.unwrap().service_status;
+    assert_eq!(status.description, "Good Service");
+    assert_eq!(
+        status.timestamp.to_rfc3339(),
+        "2024-05-02T08:01:27.354562300+00:00"
+    );
+}
+
+#[test]
+pub fn request_dates() {
+    use chrono::{FixedOffset, TimeZone};
+
+    // The same instant in three zones is always sent as UTC
+    let melbourne = chrono_tz::Australia::Melbourne
+        .with_ymd_and_hms(2024, 5, 2, 18, 5, 0)
+        .unwrap();
+    let offset = melbourne.fixed_offset();
+    let utc = melbourne.to_utc();
+    assert_eq!(offset.offset(), &FixedOffset::east_opt(10 * 3600).unwrap());
+
+    for date in [melbourne.into(), offset.into(), utc.into()] {
+        let departures = DeparturesStopOpts {
+            date: Some(date),
+            ..Default::default()
+        };
+        assert_eq!(to_query(departures), "date_utc=2024-05-02T08:05:00");
+
+        let route = RouteIdOpts {
+            date: Some(date),
+            ..Default::default()
+        };
+        assert_eq!(to_query(route), "date_utc=2024-05-02T08:05:00");
+
+        let fare = FareEstimateOpts {
+            touch_on: Some(date),
+            ..Default::default()
+        };
+        assert_eq!(to_query(fare), "journey_touch_on_utc=2024-05-02 08:05");
+    }
+
+    use serde_json::value::Serializer;
+    assert_eq!(
+        ser_iso_8601(&Some(offset), Serializer).unwrap(),
+        "2024-05-02T08:05:00"
+    );
+    assert_eq!(
+        ser_touch_utc(&Some(melbourne), Serializer).unwrap(),
+        "2024-05-02 08:05"
+    );
+    assert_eq!(
+        ser_iso_8601(&None::<RequestDate>, Serializer).unwrap(),
+        serde_json::Value::Null
+    );
+}
+
+#[test]
+pub fn service_operator() {
+    for (json, operator) in [
+        (
+            r#""Metro Trains Melbourne""#,
+            ServiceOperator::MetroTrainsMelbourne,
+        ),
+        (r#""Ventura Bus Lines""#, ServiceOperator::VenturaBusLine),
+        (r#""Kinetic""#, ServiceOperator::Kinetic),
+        (r#""CDC""#, ServiceOperator::CdcVictoria),
+        (r#""V/Line""#, ServiceOperator::VLine),
+        (
+            r#""Moonee Valley Coaches""#,
+            ServiceOperator::Other("Moonee Valley Coaches".to_string()),
+        ),
+    ] {
+        assert_eq!(load::<ServiceOperator>(json), operator);
+    }
+}
+
+#[test]
+pub fn string_enums() {
+    use DisruptionType::*;
+
+    // Ordered from least to most severe, unknown types below everything
+    let severity = [
+        Other("Something New".to_string()),
+        ServiceInformation,
+        PlannedWorks,
+        MinorDelays,
+        MajorDelays,
+        PlannedClosure,
+        PartSuspended,
+        Suspended,
+    ];
+    assert!(severity.windows(2).all(|pair| pair[0] < pair[1]));
+
+    for disruption_type in severity {
+        let json = serde_json::to_string(&disruption_type).unwrap();
+        assert_eq!(load::<DisruptionType>(&json), disruption_type);
+        assert_eq!(
+            disruption_type.to_string().parse::<DisruptionType>(),
+            Ok(disruption_type)
+        );
+    }
+    assert_eq!(" major DELAYS ".parse::<DisruptionType>(), Ok(MajorDelays));
+    assert_eq!(
+        "Line Closure".parse::<DisruptionType>(),
+        Ok(Other("Line Closure".to_string()))
+    );
+
+    for status in [
+        RunStatus::Scheduled,
+        RunStatus::Updated,
+        RunStatus::Added,
+        RunStatus::Cancelled,
+        RunStatus::Other("replaced".to_string()),
+    ] {
+        let json = serde_json::to_string(&status).unwrap();
+        assert_eq!(load::<RunStatus>(&json), status);
+    }
+    assert_eq!(load::<RunStatus>(r#""Cancelled""#), RunStatus::Cancelled);
+    assert_eq!(RunStatus::Added.to_string(), "added");
+
+    // Aliases parse, but the canonical name is sent
+    let operator = load::<ServiceOperator>(r#""Metro""#);
+    assert_eq!(operator, ServiceOperator::MetroTrainsMelbourne);
+    assert_eq!(
+        serde_json::to_string(&operator).unwrap(),
+        r#""Metro Trains Melbourne""#
+    );
+    assert_eq!(
+        ServiceOperator::Other("Moonee Valley Coaches".to_string()).as_str(),
+        "Moonee Valley Coaches"
+    );
+
+    assert_eq!(DepartureFlag::WheelchairAccessible.as_str(), "S_WCA");
+    assert_eq!("puo".parse(), Ok(DepartureFlag::PickUpOnly));
+}
+
+#[test]
+pub fn vehicle_model() {
+    for (description, model) in [
+        ("6 Car Comeng", VehicleModel::Comeng),
+        ("3 Car Siemens", VehicleModel::Siemens),
+        ("6 Car X'Trapolis", VehicleModel::XTrapolis),
+        ("7 Car HCMT", VehicleModel::Hcmt),
+        ("2 Car VLocity", VehicleModel::VLocity),
+        ("E-Class", VehicleModel::EClass),
+        ("D2", VehicleModel::DClass),
+        ("Z3 Class", VehicleModel::ZClass),
+    ] {
+        assert_eq!(description.parse::<VehicleModel>(), Ok(model));
+    }
+
+    let runs: RunsResponse = load(include_str!("fixtures/runs_train.json"));
+    let descriptor = runs.runs[0].vehicle_descriptor.as_ref().unwrap();
+    assert_eq!(descriptor.car_count(), Some(3));
+    assert_eq!(descriptor.length_metres(), Some(72.0));
+    assert_eq!(descriptor.is_step_free(), Some(true));
+
+    let pattern: PatternResponse = load(include_str!("fixtures/pattern_tram.json"));
+    let view = pattern.iter_resolved().next().unwrap();
+    assert_eq!(view.is_step_free(), Some(true));
+}
+
+#[test]
+pub fn disruptions() {
+    let response: DisruptionsResponse = load(include_str!("fixtures/disruptions.json"));
+    let disruptions = &response.disruptions;
+
+    assert!(disruptions.taxi.is_empty());
+    assert!(disruptions.other.contains_key("regional_ferry"));
+
+    assert_eq!(disruptions.len(), 3);
+    assert_eq!(disruptions.unique().count(), 2);
+    assert_eq!(disruptions.by_mode(DisruptionModes::MetroTram).count(), 1);
+
+    let severe = disruptions
+        .unique()
+        .filter(|d| d.disruption_type >= DisruptionType::MajorDelays)
+        .collect::<Vec<_>>();
+    assert_eq!(severe.len(), 1);
+    assert_eq!(severe[0].disruption_id, DisruptionId(318774));
+
+    assert_eq!(disruptions.by_mode("regional_ferry").count(), 0);
+    assert_eq!(disruptions.by_mode("not_a_mode").count(), 0);
+
+    // Pretend the tram disruption was listed under a mode we don't know about
+    let mut disruptions = response.disruptions;
+    let tram = std::mem::take(&mut disruptions.metro_tram);
+    disruptions.other.insert("regional_ferry".into(), tram);
+    assert_eq!(disruptions.by_mode("regional_ferry").count(), 1);
+    assert_eq!(disruptions.by_mode(DisruptionModes::MetroTram).count(), 0);
+
+    let modes = disruptions
+        .into_iter()
+        .map(|(mode, _)| mode)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        modes,
+        [
+            DisruptionModeKey::Known(DisruptionModes::General),
+            DisruptionModeKey::Known(DisruptionModes::MetroTrain),
+            DisruptionModeKey::from("regional_ferry"),
+        ]
+    );
+
+    let opts = DisruptionsOpts {
+        modes: Some(vec![DisruptionModes::MetroTrain, DisruptionModes::General]),
+        ..Default::default()
+    };
+    assert_eq!(to_query(opts), "disruption_modes=1&disruption_modes=100");
+}
+
+#[test]
+pub fn disruption_text() {
+    let response: DisruptionsResponse = load(include_str!("fixtures/disruptions.json"));
+    let disruption = &response.disruptions.metro_tram[0];
+
+    assert_eq!(
+        disruption.plain_text(),
+        "Route 19 trams are experiencing delays of up to 20 minutes due to a vehicle breakdown near Brunswick Rd. Passengers may choose to use alternative routes.\nCheck yarratrams.com.au for updates."
+    );
+
+    let html = disruption.sanitized_html();
+    assert!(html.contains(r#"<a href="https://yarratrams.com.au" rel="noopener noreferrer">"#));
+
+    assert_eq!(disruption.summary(30), "Route 19: Delays due to a…");
+    assert_eq!(
+        disruption.summary(200),
+        "Route 19: Delays due to a vehicle breakdown"
+    );
+}
+
+#[derive(serde::Deserialize)]
+struct ScopeCase {
+    text: String,
+    stops: Vec<(StopId, String)>,
+    segments: Vec<(String, Option<StopId>, String, Option<StopId>)>,
+    windows: usize,
+    replacement_buses: bool,
+    replacement_coaches: bool,
+}
+
+#[test]
+pub fn disruption_scope() {
+    let cases: Vec<ScopeCase> = load(include_str!("fixtures/disruption_texts.json"));
+    for case in cases {
+        let scope = DisruptionScope::extract(
+            &case.text,
+            case.stops.iter().map(|(id, name)| (*id, name.as_str())),
+        );
+
+        let segments = scope
+            .segments
+            .iter()
+            .map(|s| {
+                (
+                    s.from.name.clone(),
+                    s.from.stop_id,
+                    s.to.name.clone(),
+                    s.to.stop_id,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(segments, case.segments, "{}", case.text);
+        assert_eq!(scope.windows.len(), case.windows, "{}", case.text);
+        assert_eq!(
+            scope.replacement_buses, case.replacement_buses,
+            "{}",
+            case.text
+        );
+        assert_eq!(
+            scope.replacement_coaches, case.replacement_coaches,
+            "{}",
+            case.text
+        );
+    }
+
+    let response: PatternResponse = load(include_str!("fixtures/pattern_train.json"));
+    let scope = response.disruptions[0].scope();
+    assert_eq!(
+        scope.windows,
+        [TimeWindow {
+            start: Some(ScopeTime {
+                time: Some(TimeOfDay::At(
+                    chrono::NaiveTime::from_hms_opt(20, 30, 0).unwrap()
+                )),
+                weekday: Some(chrono::Weekday::Sat),
+                day: Some(4),
+                month: Some(chrono::Month::May),
+            }),
+            end: Some(ScopeTime {
+                time: Some(TimeOfDay::LastService),
+                weekday: Some(chrono::Weekday::Sun),
+                day: Some(5),
+                month: Some(chrono::Month::May),
+            }),
+        }]
+    );
+    assert_eq!(scope.segments[0].from.stop_id, Some(StopId(1032)));
+}
+
+#[test]
+pub fn link_disruptions() {
+    let departures: DeparturesResponse = load(include_str!("fixtures/departures_train.json"));
+    let disruptions: DisruptionsResponse = load(include_str!("fixtures/disruptions.json"));
+
+    let linked = departures.link_disruptions(&disruptions);
+    let summary = linked
+        .iter()
+        .map(|d| {
+            d.disruptions
+                .iter()
+                .map(|a| (a.disruption.disruption_id, a.referenced, a.in_effect))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        summary,
+        [
+            vec![(DisruptionId(312001), false, false)],
+            vec![(DisruptionId(312001), true, false)],
+            vec![(DisruptionId(312001), false, true)],
+        ]
+    );
+    assert!(!linked[0].is_disrupted());
+    assert!(linked[2].is_disrupted());
+}
+
+#[test]
+pub fn geojson() {
+    let routes: RoutesIdResponse = load(include_str!("fixtures/routes_geopath.json"));
+    let route = &routes.route.unwrap().route;
+    let geopath = &route.geopath.as_ref().unwrap()[0];
+
+    let lines = geo_types::MultiLineString::from(geopath);
+    assert_eq!(lines.0.len(), 2);
+    assert_eq!(lines.0[0].0.len(), 3);
+    assert_eq!(
+        lines.0[0].0[0],
+        geo_types::coord! { x: 145.079655599963, y: -37.8683203000288 }
+    );
+
+    let collection = routes_to_geojson([route]);
+    assert_eq!(collection.features.len(), 2);
+    let properties = collection.features[0].properties.as_ref().unwrap();
+    assert_eq!(properties["route_name"], "Alamein");
+    assert_eq!(properties["valid_from"], "2024-04-14");
+
+    let json = serde_json::to_value(&collection).unwrap();
+    assert_eq!(json["type"], "FeatureCollection");
+    assert_eq!(json["features"][0]["geometry"]["type"], "MultiLineString");
+    assert_eq!(
+        json["features"][0]["geometry"]["coordinates"][1][1],
+        serde_json::json!([145.082927900024, -37.8582984000176])
+    );
+
+    let departures: DeparturesResponse = load(include_str!("fixtures/departures_train.json"));
+    let json = serde_json::to_value(stops_to_geojson(departures.stops.values())).unwrap();
+    assert_eq!(json["features"].as_array().unwrap().len(), 2);
+    assert_eq!(json["features"][0]["geometry"]["type"], "Point");
+    assert_eq!(json["features"][0]["properties"]["stop_id"], 1002);
+}
+
+#[test]
+pub fn geopath_measurements() {
+    let routes: RoutesIdResponse = load(include_str!("fixtures/routes_geopath.json"));
+    let route = &routes.route.unwrap().route;
+
+    let date = |s: &str| s.parse::<chrono::NaiveDate>().unwrap();
+    let current = route.shape_for_date(date("2024-05-02")).unwrap();
+    assert_eq!(current.valid_from, date("2024-04-14"));
+    assert_eq!(
+        route.shape_for_date(date("2024-12-31")).unwrap().valid_from,
+        date("2024-07-01")
+    );
+    assert!(route.shape_for_date(date("2025-01-01")).is_none());
+
+    // Roughly 1.1km of track, Alamein towards Ashburton
+    let length = current.length_metres();
+    assert!((1_100.0..1_200.0).contains(&length), "{length}");
+
+    let bbox = current.bbox().unwrap();
+    assert_eq!(bbox.min().x, 145.079655599963);
+    assert_eq!(bbox.max().y, -37.8582984000176);
+
+    // The middle point of the first segment is ~10m off the straight line
+    let simplified = current.simplify(50.0);
+    assert_eq!(simplified.paths[0].len(), 2);
+    assert_eq!(simplified.paths[1].len(), 2);
+    assert_eq!(current.simplify(1.0).paths[0].len(), 3);
+    assert_eq!(simplified.paths[0][1], current.paths[0][2]);
+}
+
+#[test]
+pub fn geopath_encoding() {
+    let dec = |s: &str| s.parse::<Coordinate>().unwrap();
+
+    // The example from Google's polyline algorithm documentation
+    let points = [
+        ("38.5", "-120.2"),
+        ("40.7", "-120.95"),
+        ("43.252", "-126.453"),
+    ]
+    .map(|(lat, lon)| (dec(lat), dec(lon)));
+    let encoded = "_p~iF~ps|U_ulLnnqC_mqNvxq`@";
+    assert_eq!(
+        encode_polyline(&points, POLYLINE_PRECISION).unwrap(),
+        encoded
+    );
+    assert_eq!(
+        decode_polyline(encoded, POLYLINE_PRECISION).unwrap(),
+        points
+    );
+    assert!(decode_polyline("_p~iF~ps|U_", POLYLINE_PRECISION).is_err());
+
+    // Each point scales to fit in an i64 but the jump between them doesn't
+    let far = [(dec("9000"), dec("9000")), (dec("-9000"), dec("-9000"))];
+    assert!(encode_polyline(&far, MAX_PRECISION).is_err());
+    assert!(encode_polyline(&far[..1], MAX_PRECISION).is_ok());
+
+    let routes: RoutesIdResponse = load(include_str!("fixtures/routes_geopath.json"));
+    let geopath = &routes.route.unwrap().route.geopath.unwrap()[0];
+    let round = |c: Coordinate, precision| {
+        c.round_dp_with_strategy(
+            precision,
+            rust_decimal::RoundingStrategy::MidpointAwayFromZero,
+        )
+    };
+    let rounded = |precision: u32| {
+        geopath
+            .paths
+            .iter()
+            .map(|path| {
+                path.iter()
+                    .map(|(lat, lon)| (round(*lat, precision), round(*lon, precision)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let polylines = geopath.to_polyline(6).unwrap();
+    assert_eq!(polylines.len(), 2);
+    let decoded = Geopath::from_polyline(
+        geopath.direction_id,
+        geopath.valid_from,
+        geopath.valid_to,
+        &polylines,
+        6,
+    )
+    .unwrap();
+    assert_eq!(decoded.paths, rounded(6));
+
+    // Lossless at the precision the API returns
+    let bytes = geopath.to_bytes(13).unwrap();
+    assert_eq!(Geopath::from_bytes(&bytes).unwrap(), *geopath);
+
+    let bytes = geopath.to_bytes(6).unwrap();
+    let decoded = Geopath::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded.direction_id, geopath.direction_id);
+    assert_eq!(decoded.valid_to, geopath.valid_to);
+    assert_eq!(decoded.paths, rounded(6));
+    assert!(Geopath::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+}
+
+#[test]
+pub fn parse_path() {
+    let path = "-37.8683203000288, 145.079655599963 -37.8655618999753, 145.080159200029";
+    let points = ptv::parse_path(path).unwrap();
+    assert_eq!(
+        points,
+        [
+            (
+                "-37.8683203000288".parse().unwrap(),
+                "145.079655599963".parse().unwrap()
+            ),
+            (
+                "-37.8655618999753".parse().unwrap(),
+                "145.080159200029".parse().unwrap()
+            ),
+        ]
+    );
+
+    // Whole numbers and more digits than fit the fast path parse the same as FromStr
+    for value in ["145", "-0.5", "-.5", "145.0796555999630001234"] {
+        let expected = value.parse::<Coordinate>().unwrap();
+        let pair = format!("{value}, {value}");
+        assert_eq!(ptv::parse_path(&pair).unwrap(), [(expected, expected)]);
+    }
+
+    // More decimal places than Decimal can hold are rounded rather than panicking
+    let tiny = ptv::parse_path("0.00000000000000000000000000001, 145.0").unwrap();
+    assert_eq!(tiny, [(Coordinate::ZERO, "145.0".parse().unwrap())]);
+
+    assert!(ptv::parse_path("").unwrap().is_empty());
+    assert!(ptv::parse_path("-37.8683203000288,").is_err());
+    assert!(ptv::parse_path("-37.86, east").is_err());
+}
+
+#[cfg(feature = "f64-geopath")]
+#[test]
+pub fn parse_path_f64() {
+    let path = "-37.8683203000288, 145.079655599963 -37.8655618999753, 145.080159200029";
+    assert_eq!(
+        ptv::parse_path_f64(path).unwrap(),
+        [
+            (Degrees(-37.8683203000288), Degrees(145.079655599963)),
+            (Degrees(-37.8655618999753), Degrees(145.080159200029)),
+        ]
+    );
+    for value in ["145", "-.5", "145.0796555999630001234", "1e-3"] {
+        let expected = value.parse::<Degrees>().unwrap();
+        let pair = format!("{value}, {value}");
+        assert_eq!(ptv::parse_path_f64(&pair).unwrap(), [(expected, expected)]);
+    }
+    assert!(ptv::parse_path_f64("-37.86, east").is_err());
+
+    // The same shape as the Decimal geopath, to f64 precision
+    let json = include_str!("fixtures/routes_geopath.json");
+    let routes: RoutesIdResponse = load(json);
+    let geopath = &routes.route.unwrap().route.geopath.unwrap()[0];
+    let routes: RoutesIdResponse<GeopathF64> = load(json);
+    let fast = &routes.route.unwrap().route.geopath.unwrap()[0];
+    assert_eq!(fast.direction_id, geopath.direction_id);
+    assert_eq!(
+        geo_types::MultiLineString::from(fast),
+        geo_types::MultiLineString::from(geopath)
+    );
+
+    let pattern: PatternResponse<GeopathF64> =
+        load(include_str!("fixtures/pattern_train_position.json"));
+    assert!(pattern.routes[&RouteId(1)].geopath.is_some());
+}
+
+#[test]
+pub fn snap_to_pattern() {
+    let pattern: PatternResponse = load(include_str!("fixtures/pattern_train_position.json"));
+    let run = &pattern.runs[&RunRef::from("951002")];
+
+    // Between Ashburton and Camberwell, a few metres off the line
+    let snapped = run.snap_to_pattern(&pattern).unwrap();
+    assert_eq!(snapped.previous_stop.unwrap().stop.name, "Ashburton");
+    assert_eq!(snapped.next_stop.unwrap().stop.name, "Camberwell");
+    assert!(snapped.position.cross_track_error < 20.0);
+
+    let geopath = &pattern.routes[&RouteId(1)].geopath.as_ref().unwrap()[0];
+    let along = |stop: StopId| {
+        let point = pattern.stops[&stop].location().unwrap();
+        geopath.snap(point, None).unwrap()
+    };
+    let alamein = along(StopId(1002));
+    assert!(alamein.distance_along < 1.0);
+    assert!(alamein.cross_track_error < 1.0);
+    assert!(along(StopId(1012)).distance_along < snapped.position.distance_along);
+    assert!(along(StopId(1032)).distance_along > snapped.position.distance_along);
+    assert!((along(StopId(1032)).distance_along - geopath.length_metres()).abs() < 1.0);
+
+    // Still at Alamein
+    let start = geopath
+        .snap(geo_types::Point::new(145.0796, -37.8684), Some(340.0))
+        .unwrap();
+    assert!(start.distance_along < 20.0);
+
+    // Stops are taken in departure_sequence order, whatever order the response lists them in
+    let mut shuffled: PatternResponse = load(include_str!("fixtures/pattern_train_position.json"));
+    shuffled.departures.reverse();
+    let run = &shuffled.runs[&RunRef::from("951002")];
+    let snapped = run.snap_to_pattern(&shuffled).unwrap();
+    assert_eq!(snapped.previous_stop.unwrap().stop.name, "Ashburton");
+    assert_eq!(snapped.next_stop.unwrap().stop.name, "Camberwell");
+
+    // The line split in two carries on from one path to the next,
+    // but a separate branch (here ~900m east) is measured from its own start
+    let line = &geopath.paths[0];
+    let east = "0.01".parse::<Coordinate>().unwrap();
+    let branched = Geopath {
+        direction_id: geopath.direction_id,
+        valid_from: geopath.valid_from,
+        valid_to: geopath.valid_to,
+        paths: vec![
+            line[..6].to_vec(),
+            line[5..].to_vec(),
+            line[5..]
+                .iter()
+                .map(|(lat, lon)| (*lat, lon + east))
+                .collect(),
+        ],
+    };
+    let camberwell = pattern.stops[&StopId(1032)].location().unwrap();
+    let end = branched.snap(camberwell, None).unwrap();
+    assert_eq!(end.path, 1);
+    assert!((end.distance_along - geopath.length_metres()).abs() < 1.0);
+    let branch = branched
+        .snap(
+            geo_types::Point::new(camberwell.x() + 0.01, camberwell.y()),
+            None,
+        )
+        .unwrap();
+    assert_eq!(branch.path, 2);
+    let branch_length = Geopath {
+        paths: vec![line[5..].to_vec()],
+        ..branched
+    }
+    .length_metres();
+    assert!((branch.distance_along - branch_length).abs() < 5.0);
+    assert!(branch.distance_along < end.distance_along - 1000.0);
+}
+
+#[test]
+pub fn grid_coordinates() {
+    let close = |point: geo_types::Point<f64>, longitude: f64, latitude: f64| {
+        (point.x() - longitude).abs() < 1e-7 && (point.y() - latitude).abs() < 1e-7
+    };
+    let dms = |d: f64, m: f64, s: f64| -(d + m / 60.0 + s / 3600.0);
+
+    // Flinders Peak, the worked example from the GDA technical manual
+    let flinders_peak = Grid::Mga55.to_wgs84(273_741.297, 5_796_489.777);
+    assert!(
+        close(
+            flinders_peak,
+            -dms(144.0, 25.0, 29.5244),
+            dms(37.0, 57.0, 3.7203)
+        ),
+        "{flinders_peak:?}"
+    );
+    assert_eq!(Grid::detect(273_741.297, 5_796_489.777), Some(Grid::Mga55));
+
+    // VicGrid's false origin
+    let origin = Grid::VicGrid94.to_wgs84(2_500_000.0, 2_500_000.0);
+    assert!(close(origin, 145.0, -37.0), "{origin:?}");
+    let flinders_street = Grid::VicGrid94.to_wgs84(2_497_103.342_5, 2_409_190.918_7);
+    assert!(
+        close(flinders_street, 144.9671, -37.8183),
+        "{flinders_street:?}"
+    );
+    assert_eq!(
+        Grid::detect(2_500_000.0, 2_500_000.0),
+        Some(Grid::VicGrid94)
+    );
+    assert_eq!(Grid::detect(0.0, 0.0), None);
+
+    // Only an easting and northing from CIS
+    let runs: RunsResponse = load(include_str!("fixtures/runs_train.json"));
+    let position = runs.runs[0].vehicle_position.as_ref().unwrap();
+    let point = position.coordinates().unwrap();
+    assert!((144.9..145.2).contains(&point.x()), "{point:?}");
+    assert!((-38.0..-37.7).contains(&point.y()), "{point:?}");
+}
+
+#[test]
+pub fn stop_index() {
+    let train: PatternResponse = load(include_str!("fixtures/pattern_train.json"));
+    let tram: PatternResponse = load(include_str!("fixtures/pattern_tram.json"));
+    let index = train
+        .stops
+        .values()
+        .chain(tram.stops.values())
+        .collect::<StopIndex<_>>();
+    assert_eq!(index.len(), 5);
+
+    // Between Alamein and Ashburton
+    let point = geo_types::Point::new(145.0805, -37.8645);
+    let within = index.within(point, 500.0, None);
+    let ids = within.iter().map(|s| s.stop.stop.id).collect::<Vec<_>>();
+    assert_eq!(ids, [StopId(1012), StopId(1002)]);
+    assert!(within[0].distance < within[1].distance && within[1].distance <= 500.0);
+    assert!(index.within(point, 200.0, None).is_empty());
+
+    let nearest_trams = index.nearest(point, 5, Some(RouteType::Tram));
+    let ids = nearest_trams
+        .iter()
+        .map(|s| s.stop.stop.id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [StopId(2161), StopId(2160)]);
+    // Roughly 17km across town
+    assert!((16_500.0..17_000.0).contains(&nearest_trams[0].distance));
+
+    let nearest = index.nearest(point, 1, None);
+    assert_eq!(nearest[0].stop.stop.id, StopId(1012));
+}
+
+#[test]
+pub fn line_diagram() {
+    let pattern: PatternResponse = load(include_str!("fixtures/pattern_train_skipped.json"));
+    let style = DiagramStyle::default();
+    let svg = pattern.line_diagram(pattern.runs.values(), &style);
+
+    assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>"));
+    let names = [
+        "Alamein",
+        "Ashburton",
+        "Willison",
+        "Riversdale",
+        "Camberwell",
+    ]
+    .map(|name| svg.find(&format!(">{name}</text>")).unwrap());
+    assert!(names.is_sorted());
+
+    // Skipped stops are greyed out
+    let skipped = format!(r#"fill="{}">Willison</text>"#, style.skipped_colour);
+    assert!(svg.contains(&skipped));
+    assert!(svg.contains(&format!(
+        r#"fill="{}">Camberwell</text>"#,
+        style.text_colour
+    )));
+
+    // The train between Ashburton (y=36) and Camberwell (y=196)
+    let vehicle = svg.split(r#"<circle cx="29.0" cy=""#).last().unwrap();
+    assert!(vehicle.contains("<title>951002 to Flinders Street</title>"));
+    let y = vehicle.split('"').next().unwrap().parse::<f64>().unwrap();
+    assert!((76.0..196.0).contains(&y), "{y}");
+
+    let horizontal = DiagramStyle {
+        orientation: Orientation::Horizontal,
+        background: Some("black".into()),
+        ..Default::default()
+    };
+    let svg = pattern.line_diagram([], &horizontal);
+    assert!(svg.contains(r#"<rect width="100%" height="100%" fill="black"/>"#));
+    assert!(svg.contains("rotate(-45"));
+    assert!(!svg.contains("<title>"));
+}
+
+#[test]
+pub fn kml_and_gpx() {
+    let routes: RoutesIdResponse = load(include_str!("fixtures/routes_geopath.json"));
+    let route = &routes.route.unwrap().route;
+    let departures: DeparturesResponse = load(include_str!("fixtures/departures_train.json"));
+
+    // Samples out of order, and one with no location at all
+    let positions: Vec<VehiclePosition> = load(
+        r#"[
+            {"latitude": -37.8401, "longitude": 145.0732, "easting": null, "northing": null, "direction": null,
+             "bearing": 330, "supplier": "CIS", "datetime_utc": "2024-05-02T08:08:40Z", "expiry_time": null},
+            {"latitude": -37.8432, "longitude": 145.0751, "easting": null, "northing": null, "direction": null,
+             "bearing": 330, "supplier": "CIS", "datetime_utc": "2024-05-02T08:08:10Z", "expiry_time": null},
+            {"latitude": null, "longitude": null, "easting": null, "northing": null, "direction": null,
+             "bearing": null, "supplier": "CIS", "datetime_utc": "2024-05-02T08:09:10Z", "expiry_time": null}
+        ]"#,
+    );
+
+    let kml = Kml::new("Alamein & Glen Waverley")
+        .add_route(route)
+        .add_stops(departures.stops.values())
+        .add_track("951002", &positions)
+        .to_string();
+    assert!(kml.contains("<Document><name>Alamein &amp; Glen Waverley</name>"));
+    assert_eq!(kml.matches("<Placemark>").count(), 2 + 2 + 1);
+    assert_eq!(kml.matches("<LineString>").count(), 3);
+    assert!(kml.contains("145.079655599963,-37.8683203000288 "));
+    assert!(kml.contains(
+        "<when>2024-05-02T08:08:10Z</when><when>2024-05-02T08:08:40Z</when><gx:coord>145.0751 -37.8432 0</gx:coord>"
+    ));
+
+    let gpx = Gpx::new("Alamein")
+        .add_track("951002", &positions)
+        .add_stops(departures.stops.values())
+        .add_route(route)
+        .to_string();
+    // Waypoints come before tracks, whatever order they're added in
+    assert!(gpx.find("<wpt").unwrap() < gpx.find("<trk>").unwrap());
+    assert_eq!(gpx.matches("<trk>").count(), 1 + 2);
+    assert_eq!(gpx.matches("<trkseg>").count(), 1 + 3);
+    assert!(gpx.contains(r#"<trkpt lat="-37.8683203000288" lon="145.079655599963"/>"#));
+    assert!(gpx.contains(
+        r#"<trkpt lat="-37.8432" lon="145.0751"><time>2024-05-02T08:08:10Z</time></trkpt>"#
+    ));
+}
+
+#[test]
+pub fn gtfs() {
+    let directory = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/gtfs");
+    let gtfs = Gtfs::open(directory).unwrap();
+
+    assert_eq!(gtfs.routes.len(), 2);
+    let alamein = &gtfs.route_by_gtfs_id("2-ALM").unwrap().route;
+    assert_eq!(alamein.gtfs_id, "2-ALM-mjp-1");
+    assert_eq!(alamein.route_type, RouteType::Train);
+    let tram = &gtfs.route_by_gtfs_id("3-19-mjp-1").unwrap();
+    assert_eq!(
+        (tram.route.route_type, tram.route.number.as_str()),
+        (RouteType::Tram, "19")
+    );
+    assert!(tram.geopath.is_none());
+
+    // Stops nothing calls at are left out, stop IDs that aren't numbers are made up
+    assert_eq!(gtfs.stops.len(), 5);
+    assert_eq!(gtfs.stops[&StopId(1002)].landmark, "Alamein Avenue");
+    let flinders = gtfs.stop_id("19-FSS").unwrap();
+    assert_eq!(flinders, StopId(-1));
+    assert_eq!(gtfs.gtfs_stop_id(flinders), Some("19-FSS"));
+    assert_eq!(gtfs.stops[&flinders].route_type, RouteType::Tram);
+
+    let directions = gtfs.route_directions(alamein.id).collect::<Vec<_>>();
+    assert_eq!(directions.len(), 2);
+    assert_eq!(directions[0].name, "Camberwell");
+    assert_eq!(directions[1].name, "Alamein Station");
+
+    let geopaths = gtfs.routes[&alamein.id].geopath.as_ref().unwrap();
+    assert_eq!(geopaths.len(), 2);
+    assert_eq!(geopaths[0].direction_id, directions[0].id);
+    assert_eq!(geopaths[0].paths[0].len(), 3);
+    assert_eq!(geopaths[0].valid_from.to_string(), "2024-04-01");
+    assert_eq!(geopaths[0].valid_to.to_string(), "2024-06-30");
+
+    let date = |s: &str| s.parse::<chrono::NaiveDate>().unwrap();
+    let departures = gtfs.departures(date("2024-05-02"));
+    assert_eq!(departures.len(), 7);
+    let first = &departures[0];
+    assert_eq!(first.stop_id, StopId(1002));
+    assert_eq!(first.run_ref, RunRef::from("ALM-0800"));
+    assert_eq!(first.platform_number.as_deref(), Some("1"));
+    assert_eq!(
+        first.scheduled_departure.unwrap().to_rfc3339(),
+        "2024-05-01T22:00:00+00:00"
+    );
+    // 24:36 on the service day is 00:36 the next morning
+    let last = departures.last().unwrap();
+    assert_eq!(
+        last.scheduled_departure_local().unwrap().to_rfc3339(),
+        "2024-05-03T00:36:00+10:00"
+    );
+    assert!(last.flags.is_drop_off_only());
+    assert!(
+        departures
+            .iter()
+            .find(|d| d.stop_id == flinders)
+            .unwrap()
+            .flags
+            .is_wheelchair_accessible()
+    );
+
+    assert!(gtfs.departures(date("2024-04-25")).is_empty());
+    assert_eq!(gtfs.departures(date("2024-04-28")).len(), 7);
+    let camberwell = gtfs.stop_departures(StopId(1030), date("2024-05-02"));
+    assert_eq!(camberwell.len(), 2);
+    assert_eq!(camberwell[0].departure_sequence, 3);
+
+    // The same feed zipped, inside a zip of feeds as PTV publish them
+    let zip = |files: Vec<(String, Vec<u8>)>| {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, data) in files {
+            writer
+                .start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            std::io::Write::write_all(&mut writer, &data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    };
+    let feed = zip(std::fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, std::fs::read(path).unwrap())
+        })
+        .collect());
+    let feeds = zip(vec![("2/google_transit.zip".into(), feed)]);
+    let zipped = Gtfs::from_zip(std::io::Cursor::new(feeds)).unwrap();
+    assert_eq!(zipped.stops.len(), 5);
+    assert_eq!(zipped.departures(date("2024-05-02")).len(), 7);
+}
+
+#[test]
+pub fn gtfs_realtime() {
+    use prost::Message;
+    use realtime::proto;
+
+    let pattern: PatternResponse = load(include_str!("fixtures/pattern_train_position.json"));
+    let departures: DeparturesResponse = load(include_str!("fixtures/departures_train.json"));
+    let disruptions: DisruptionsResponse = load(include_str!("fixtures/disruptions.json"));
+    let now = "2024-05-02T08:08:30Z"
+        .parse::<chrono::DateTime<chrono::Utc>>()
+        .unwrap();
+
+    let bytes = RealtimeFeed::new()
+        .add_pattern(&pattern)
+        .add_departures(&departures)
+        .add_disruptions(disruptions.disruptions.unique())
+        .encode(now);
+    let feed = proto::FeedMessage::decode(bytes.as_slice()).unwrap();
+    assert_eq!(feed.header.gtfs_realtime_version, "2.0");
+    assert_eq!(feed.header.timestamp, Some(now.timestamp() as u64));
+    // 951040 has no estimates, so no trip update
+    let ids = feed
+        .entity
+        .iter()
+        .map(|e| e.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ids,
+        [
+            "trip-951002",
+            "vehicle-951002",
+            "alert-312001",
+            "alert-318774"
+        ]
+    );
+
+    let update = feed.entity[0].trip_update.as_ref().unwrap();
+    assert_eq!(update.trip.trip_id.as_deref(), Some("951002"));
+    assert_eq!(update.trip.route_id.as_deref(), Some("2-ALM"));
+    assert_eq!(update.trip.start_date.as_deref(), Some("20240502"));
+    assert_eq!(update.trip.start_time.as_deref(), Some("18:02:00"));
+    // The same stops from the departures response are merged in, not repeated
+    assert_eq!(update.stop_time_update.len(), 2);
+    let first = &update.stop_time_update[0];
+    assert_eq!(first.stop_id.as_deref(), Some("1002"));
+    assert_eq!(first.stop_sequence, Some(1));
+    let departure = first.departure.as_ref().unwrap();
+    assert_eq!(departure.delay, Some(60));
+    assert_eq!(
+        departure.time,
+        Some(
+            "2024-05-02T08:03:00Z"
+                .parse::<chrono::DateTime<chrono::Utc>>()
+                .unwrap()
+                .timestamp()
+        )
+    );
+
+    let vehicle = feed.entity[1].vehicle.as_ref().unwrap();
+    let position = vehicle.position.as_ref().unwrap();
+    assert!((position.latitude - -37.8401).abs() < 1e-4);
+    assert_eq!(position.bearing, Some(330.0));
+    assert_eq!(vehicle.stop_id.as_deref(), Some("1032"));
+    assert_eq!(
+        vehicle.current_status,
+        Some(proto::VehicleStopStatus::InTransitTo.into())
+    );
+
+    let works = feed.entity[2].alert.as_ref().unwrap();
+    assert_eq!(works.cause, Some(proto::Cause::Maintenance.into()));
+    assert_eq!(works.effect, Some(proto::Effect::ModifiedService.into()));
+    // Routes and stops are informed separately, not every stop paired with every route
+    let informed = works
+        .informed_entity
+        .iter()
+        .map(|e| (e.route_id.as_deref(), e.stop_id.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        informed,
+        [
+            (Some("2-ALM"), None),
+            (None, Some("1002")),
+            (None, Some("1032"))
+        ]
+    );
+    let delays = feed.entity[3].alert.as_ref().unwrap();
+    assert_eq!(delays.effect, Some(proto::Effect::SignificantDelays.into()));
+    assert!(delays.informed_entity.iter().all(|e| e.stop_id.is_none()));
+
+    // With a static feed, trips are found by route, stop and scheduled time, even after midnight
+    let gtfs = Gtfs::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/gtfs")).unwrap();
+    let route = &departures.routes[&RouteId(1)].route;
+    let date = "2024-05-02".parse::<chrono::NaiveDate>().unwrap();
+    let late = &gtfs.stop_departures(StopId(1012), date)[1];
+    assert_eq!(gtfs.find_trip(route, late), Some(&RunRef::from("ALM-2430")));
+    assert_eq!(
+        gtfs.find_trip_date(route, late),
+        Some((&RunRef::from("ALM-2430"), date))
+    );
+    assert_eq!(gtfs.find_trip(route, &departures.departures[0]), None);
+
+    // A run leaving just after midnight starts after 24:00:00 on the service day before,
+    // guessed without a static feed and looked up with one
+    let mut night: PatternResponse = load(include_str!("fixtures/pattern_train_position.json"));
+    for departure in &mut night.departures {
+        let shift = |time: chrono::DateTime<chrono::Utc>| time + chrono::Duration::minutes(390);
+        departure.scheduled_departure = departure.scheduled_departure.map(shift);
+        departure.estimated_departure = departure.estimated_departure.map(shift);
+    }
+    // The API's departure_sequence only goes with its own trip_id
+    for (feed, trip_id, stop_sequence) in [
+        (RealtimeFeed::new(), "951002", Some(1)),
+        (RealtimeFeed::with_gtfs(&gtfs), "ALM-2430", None),
+    ] {
+        let mut feed = feed;
+        let message = feed.add_pattern(&night).to_message(now);
+        let update = message.entity[0].trip_update.as_ref().unwrap();
+        let trip = &update.trip;
+        assert_eq!(trip.trip_id.as_deref(), Some(trip_id));
+        assert_eq!(trip.start_time.as_deref(), Some("24:32:00"));
+        assert_eq!(trip.start_date.as_deref(), Some("20240502"));
+        assert_eq!(update.stop_time_update[0].stop_sequence, stop_sequence);
+    }
+
+    // Seen only from its stops, a run's service day still comes from the static feed
+    let mut night: DeparturesResponse = load(include_str!("fixtures/departures_train.json"));
+    for departure in &mut night.departures {
+        let shift = |time: chrono::DateTime<chrono::Utc>| time + chrono::Duration::minutes(390);
+        departure.scheduled_departure = departure.scheduled_departure.map(shift);
+        departure.estimated_departure = departure.estimated_departure.map(shift);
+    }
+    let message = RealtimeFeed::with_gtfs(&gtfs)
+        .add_departures(&night)
+        .to_message(now);
+    let trip = &message.entity[0].trip_update.as_ref().unwrap().trip;
+    assert_eq!(trip.trip_id.as_deref(), Some("ALM-2430"));
+    assert_eq!(trip.start_time, None);
+    assert_eq!(trip.start_date.as_deref(), Some("20240502"));
+
+    // A run cancelled since it was last seen loses its stop times
+    let mut cancelled = departures;
+    cancelled
+        .runs
+        .get_mut(&RunRef::from("951002"))
+        .unwrap()
+        .status = RunStatus::Cancelled;
+    let message = RealtimeFeed::new()
+        .add_pattern(&pattern)
+        .add_departures(&cancelled)
+        .to_message(now);
+    let update = message.entity[0].trip_update.as_ref().unwrap();
+    assert_eq!(
+        update.trip.schedule_relationship,
+        Some(proto::TripScheduleRelationship::Canceled.into())
+    );
+    assert!(update.stop_time_update.is_empty());
+
+    // Selectors with no ID are left out, along with alerts left with nothing to inform
+    let mut disruptions = disruptions;
+    disruptions.disruptions.metro_tram[0].routes[0]
+        .route
+        .gtfs_id
+        .clear();
+    let message = RealtimeFeed::new()
+        .add_disruptions(&disruptions.disruptions.metro_tram)
+        .to_message(now);
+    assert!(message.entity.is_empty());
+}
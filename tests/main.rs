@@ -1,4 +1,4 @@
-#[allow(dead_code)]
+#[allow(dead_code, clippy::needless_update)]
 #[cfg(test)]
 pub mod test {
     use std::{collections::BTreeMap, future::Future, pin::Pin, sync::Arc};
@@ -29,7 +29,7 @@ pub mod test {
     static ROUTE_ID: RouteId = RouteId(1); // Alamein (Line)
     static STOP_ID: StopId = StopId(1002); // Alamein (Station)
     static DIRECTION_ID: DirectionId = DirectionId(1); // Towards Flinders Street
-    static RUN_REF: &str = "1"; // Alamein something
+    static RUN_REF: Lazy<RunRef> = Lazy::new(|| RunRef::from("1")); // Alamein something
 
     type Task =
         Arc<dyn Fn() -> Pin<Box<dyn Future<Output = anyhow::Result<String>>>> + Send + Sync>;
@@ -63,7 +63,7 @@ pub mod test {
         make_test!(map, routes_id,  RouteIdOpts => [include_geopath], ROUTE_ID);
 
         // > Patterns
-        make_test!(map, patterns_run_route, PatternsRunRouteOpts => [stop_id: STOP_ID, expand: vec![ExpandOptions::All], include_skipped, include_geopath], RUN_REF, ROUTE_TYPE);
+        make_test!(map, patterns_run_route, PatternsRunRouteOpts => [stop_id: STOP_ID, expand: vec![ExpandOptions::All], include_skipped, include_geopath], &RUN_REF, ROUTE_TYPE);
 
         // > Directions
 